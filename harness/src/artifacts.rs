@@ -0,0 +1,101 @@
+//! Proofs, verifying keys and public outputs kept on disk with a manifest, so they can be
+//! audited and verified again later.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{record::BackendInfo, Backend};
+
+/// Name of the manifest in a cell's artifact directory.
+pub const MANIFEST: &str = "manifest.json";
+
+/// Roles of the files in [`Manifest::files`].
+pub const PROOF: &str = "proof";
+pub const VK: &str = "vk";
+pub const PUBLIC: &str = "public";
+
+/// What a cell's artifact directory holds and where it came from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub task: String,
+    pub backend: String,
+    pub variant: String,
+    pub size: usize,
+    /// Seed the proven input was generated with, see [`crate::corpus`].
+    pub seed: u64,
+    /// See [`crate::Backend::program_id`].
+    pub program_id: String,
+    /// Harness and proving library versions.
+    pub versions: BTreeMap<String, String>,
+    /// Files by role, [`PROOF`], [`VK`] or [`PUBLIC`].
+    pub files: BTreeMap<String, Artifact>,
+}
+
+/// One file of an artifact directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Relative to the manifest.
+    pub path: PathBuf,
+    pub bytes: usize,
+    /// Hex SHA-256 of the file.
+    pub sha256: String,
+}
+
+impl Manifest {
+    /// Reads the manifest of the artifact directory `dir`.
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST);
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("cannot read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("invalid {}", path.display()))
+    }
+}
+
+/// Saves `proof` of the `size` input generated from `seed`, the verifying key and the public
+/// output into `dir`, then writes the manifest listing them.
+pub fn save<B: Backend>(
+    backend: &B,
+    size: usize,
+    seed: u64,
+    proof: &B::Proof,
+    dir: &Path,
+) -> Result<Manifest> {
+    fs::create_dir_all(dir).with_context(|| format!("cannot create {}", dir.display()))?;
+    backend.save_proof(proof, &dir.join(PROOF))?;
+    fs::write(dir.join(VK), backend.verifying_key()?)?;
+    fs::write(dir.join(PUBLIC), backend.public_output(proof)?)?;
+
+    let mut files = BTreeMap::new();
+    for role in [PROOF, VK, PUBLIC] {
+        let bytes = fs::read(dir.join(role))?;
+        let artifact = Artifact {
+            path: role.into(),
+            bytes: bytes.len(),
+            sha256: Sha256::digest(&bytes)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+        };
+        files.insert(role.to_string(), artifact);
+    }
+    let info = BackendInfo::of(backend);
+    let manifest = Manifest {
+        task: info.task,
+        backend: info.backend,
+        variant: info.variant,
+        size,
+        seed,
+        program_id: backend.program_id()?,
+        versions: info.versions,
+        files,
+    };
+    fs::write(dir.join(MANIFEST), serde_json::to_string_pretty(&manifest)?)?;
+    Ok(manifest)
+}
@@ -0,0 +1,125 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, ensure, Result};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+/// One cell of the benchmark matrix: a task (fibo, sha2, ...) proven with one backend
+/// (halo2, risc0, sp1, ...).
+///
+/// Implementations only describe how to set up, generate keys, prove and verify. The warmup and timing
+/// loop lives in [`crate::run`], so all cells are measured the same way.
+pub trait Backend {
+    /// Task input for a given size, e.g. `n` for fibo or the message bytes for sha2.
+    type Input;
+    /// Proof produced by [`Backend::prove`], e.g. a halo2 snark or a risc0 receipt.
+    type Proof;
+
+    /// Name of the benchmarked task, e.g. `fibo`.
+    fn task(&self) -> &str;
+
+    /// Name of the proving backend, e.g. `sp1`.
+    fn name(&self) -> &str;
+
+    /// Proof system flavour within the backend, e.g. `core` or `groth16` for SP1.
+    fn variant(&self) -> &str;
+
+    /// Versions of the proving libraries, recorded next to every measurement.
+    fn versions(&self) -> Vec<(&'static str, &'static str)>;
+
+    /// Applies the knobs of the `[tasks.<task>.backends.<backend>]` table of the runner's
+    /// config, as a JSON object. Runs before anything else. Backends without knobs reject
+    /// any; the others deserialize them with [`params`].
+    fn configure(&mut self, params: &serde_json::Value) -> Result<()> {
+        ensure!(
+            params.as_object().is_none_or(|p| p.is_empty()),
+            "{}/{} takes no params, got {params}",
+            self.task(),
+            self.name()
+        );
+        Ok(())
+    }
+
+    /// Checks that the knobs can prove an input of `size`, e.g. that a circuit is large
+    /// enough for it. Runs right after [`Backend::configure`] for every requested size, so a
+    /// sweep fails before its first cell rather than midway.
+    fn check_size(&self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Builds the task input for `size`. Random inputs must come from [`crate::corpus`] with
+    /// `seed`, so every backend of a task proves the same input.
+    fn input(&self, size: usize, seed: u64) -> Self::Input;
+
+    /// Generates or loads the universal parameters for `size`, e.g. a KZG SRS, or builds
+    /// the prover client. Nothing here may depend on the circuit or program.
+    fn setup(&mut self, size: usize) -> Result<()>;
+
+    /// Generates the circuit or program specific proving and verifying keys for `size`.
+    /// Runs after [`Backend::setup`].
+    fn keygen(&mut self, size: usize) -> Result<()>;
+
+    /// Runs the workload without proving it, e.g. the zkVM executor, or `None` if the backend
+    /// cannot. Timed next to [`Backend::prove`] to tell execution from proving overhead.
+    fn execute(&self, _input: &Self::Input) -> Option<Result<()>> {
+        None
+    }
+
+    fn prove(&self, input: &Self::Input) -> Result<Self::Proof>;
+
+    fn verify(&self, proof: &Self::Proof) -> Result<()>;
+
+    /// Decodes the public output of `proof` and compares it with the
+    /// [`crate::reference`] result for `input`, failing the cell on mismatch.
+    fn check_output(&self, input: &Self::Input, proof: &Self::Proof) -> Result<()>;
+
+    /// Public output committed by the proof (journal, public values or instances).
+    fn public_output(&self, proof: &Self::Proof) -> Result<Vec<u8>>;
+
+    /// Proof bytes as they would be sent to a verifier.
+    fn serialize_proof(&self, proof: &Self::Proof) -> Result<Vec<u8>>;
+
+    /// Serialized verifying key. For the zkVMs this is whatever identifies the program to
+    /// the verifier, e.g. the risc0 image ID.
+    fn verifying_key(&self) -> Result<Vec<u8>>;
+
+    /// Serialized size of the proving key, `None` if the backend has no program-specific
+    /// proving key.
+    fn proving_key_size(&self) -> Result<Option<usize>>;
+
+    /// Writes `proof` to `path` in a format the backend's own tooling reads back, e.g. a
+    /// halo2 snark file. Defaults to [`Backend::serialize_proof`].
+    fn save_proof(&self, proof: &Self::Proof, path: &Path) -> Result<()> {
+        fs::write(path, self.serialize_proof(proof)?)?;
+        Ok(())
+    }
+
+    /// Reads a proof written by [`Backend::save_proof`], or `None` if the backend has no
+    /// proofs to verify again, like the native baselines.
+    fn load_proof(&self, _path: &Path) -> Option<Result<Self::Proof>> {
+        None
+    }
+
+    /// Makes [`Backend::verify`] check proofs against `vk`, a key saved from
+    /// [`Backend::verifying_key`] for `size`, instead of the one of [`Backend::keygen`]. Runs
+    /// after [`Backend::setup`], so saved proofs are verified against the key they were made
+    /// with even when the current circuit or program would give another.
+    fn load_verifying_key(&mut self, _size: usize, _vk: &[u8]) -> Result<()> {
+        bail!("{}/{} cannot load verifying keys", self.task(), self.name())
+    }
+
+    /// What identifies the proven program or circuit, recorded next to every measurement.
+    /// Runs after [`Backend::keygen`]. Defaults to the hex SHA-256 of
+    /// [`Backend::verifying_key`]; the zkVMs with a natural identifier, like the risc0 image
+    /// ID, return that instead.
+    fn program_id(&self) -> Result<String> {
+        let hash = Sha256::digest(self.verifying_key()?);
+        Ok(hash.iter().map(|b| format!("{b:02x}")).collect())
+    }
+}
+
+/// Deserializes the params given to [`Backend::configure`], naming the offending field on
+/// error.
+pub fn params<T: DeserializeOwned>(params: &serde_json::Value) -> Result<T> {
+    Ok(serde_json::from_value(params.clone())?)
+}
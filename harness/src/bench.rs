@@ -0,0 +1,297 @@
+use std::{fs, path::Path, time::Instant};
+
+use anyhow::{bail, ensure, Result};
+
+use crate::{
+    artifacts::{self, Manifest},
+    mem,
+    record::{BackendInfo, Kind, Phase, Record, Writer},
+    stats::{self, Summary},
+    Backend, Sizes,
+};
+
+/// Input sizes every task is benchmarked with unless told otherwise.
+pub const SIZES: [usize; 5] = [10, 100, 1000, 10_000, 100_000];
+
+/// How each cell is run.
+#[derive(Clone, Copy, Debug, clap::Args)]
+pub struct Options {
+    /// Untimed prove/verify rounds before sampling.
+    #[arg(long, default_value_t = 1)]
+    pub warmups: usize,
+
+    /// Timed prove/verify rounds per size, at least one.
+    #[arg(long, default_value_t = 3, value_parser = crate::cli::at_least_one)]
+    pub samples: usize,
+
+    /// Seed of the task inputs, see [`crate::corpus`].
+    #[arg(long, default_value_t = crate::corpus::DEFAULT_SEED)]
+    pub seed: u64,
+}
+
+impl Options {
+    /// The command line flags that reproduce these options.
+    pub fn to_args(&self) -> Vec<String> {
+        vec![
+            "--warmups".into(),
+            self.warmups.to_string(),
+            "--samples".into(),
+            self.samples.to_string(),
+            "--seed".into(),
+            self.seed.to_string(),
+        ]
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            warmups: 1,
+            samples: 3,
+            seed: crate::corpus::DEFAULT_SEED,
+        }
+    }
+}
+
+/// Wall-clock time and peak memory of one run of a phase.
+#[derive(Clone, Copy)]
+struct Measured {
+    nanos: u64,
+    peak_rss: Option<u64>,
+}
+
+fn measure<T>(f: impl FnOnce() -> Result<T>) -> Result<(T, Measured)> {
+    mem::reset_peak();
+    let t = Instant::now();
+    let out = f()?;
+    let nanos = t.elapsed().as_nanos() as u64;
+    let peak_rss = mem::peak_rss();
+    Ok((out, Measured { nanos, peak_rss }))
+}
+
+/// Timings of one execute/prove/verify round.
+#[derive(Clone, Copy)]
+struct Round {
+    /// `None` if the backend cannot execute without proving.
+    execute: Option<Measured>,
+    prove: Measured,
+    verify: Measured,
+}
+
+/// Executes, proves and verifies `input` once, then checks the public output of the proof.
+fn round<B: Backend>(backend: &B, input: &B::Input) -> Result<(B::Proof, Round)> {
+    let (executed, execute) = measure(|| backend.execute(input).transpose())?;
+    let (proof, prove) = measure(|| backend.prove(input))?;
+    let ((), verify) = measure(|| backend.verify(&proof))?;
+    backend.check_output(input, &proof)?;
+    let execute = executed.map(|()| execute);
+    Ok((
+        proof,
+        Round {
+            execute,
+            prove,
+            verify,
+        },
+    ))
+}
+
+/// Sets up `backend` and generates its keys for `size`, runs the warmups, then times `samples` prove/verify
+/// rounds. Returns the cold first round, one record per sample and one summary per phase.
+/// The last proof is saved with its verifying key and public output into `artifacts`, if given.
+pub fn run_cell<B: Backend>(
+    backend: &mut B,
+    size: usize,
+    opts: &Options,
+    artifacts: Option<&Path>,
+) -> Result<Vec<Record>> {
+    let ((), setup) = measure(|| backend.setup(size))?;
+    let ((), keygen) = measure(|| backend.keygen(size))?;
+    let input = backend.input(size, opts.seed);
+
+    // the first round of the process is the cold start: the first warmup, or the first
+    // sample without warmups
+    let mut cold = None;
+
+    //warmup
+    for _ in 0..opts.warmups {
+        let (_, r) = round(backend, &input)?;
+        cold.get_or_insert(r);
+    }
+
+    //benchmark running
+    let mut execute = Vec::with_capacity(opts.samples);
+    let mut prove = Vec::with_capacity(opts.samples);
+    let mut verify = Vec::with_capacity(opts.samples);
+    let mut sizes = Sizes {
+        vk_bytes: Some(backend.verifying_key()?.len()),
+        pk_bytes: backend.proving_key_size()?,
+        ..Default::default()
+    };
+    let mut last = None;
+    for _ in 0..opts.samples {
+        let (proof, r) = round(backend, &input)?;
+        cold.get_or_insert(r);
+        execute.extend(r.execute);
+        prove.push(r.prove);
+        verify.push(r.verify);
+        sizes.proof_bytes = Some(backend.serialize_proof(&proof)?.len());
+        sizes.public_input_bytes = Some(backend.public_output(&proof)?.len());
+        last = Some(proof);
+    }
+    if let (Some(dir), Some(proof)) = (artifacts, &last) {
+        artifacts::save(backend, size, opts.seed, proof, dir)?;
+    }
+
+    let info = BackendInfo::of(backend);
+    let program_id = Some(backend.program_id()?);
+    let mut records = vec![];
+    // setup and keygen run once, they are always cold
+    for (phase, cold, measured) in [
+        (Phase::Setup, None, vec![setup]),
+        (Phase::Keygen, None, vec![keygen]),
+        (Phase::Execute, cold.and_then(|r| r.execute), execute),
+        (Phase::Prove, cold.map(|r| r.prove), prove),
+        (Phase::Verify, cold.map(|r| r.verify), verify),
+    ] {
+        let record = |kind, peak_rss_bytes| Record {
+            sizes: sizes.clone(),
+            peak_rss_bytes,
+            program_id: program_id.clone(),
+            seed: Some(opts.seed),
+            ..info.record(size, Some(phase), kind)
+        };
+        if measured.is_empty() {
+            // the backend cannot execute without proving
+            continue;
+        }
+        records.extend(phase_records(record, cold, &measured));
+    }
+    Ok(records)
+}
+
+/// Verifies the proof saved in the artifact directory `dir`, see [`crate::artifacts`]: sets
+/// up for its size and loads the saved verifying key, then times `samples` verifications
+/// after the warmups. Fails if the public output differs from the saved one. With
+/// `check_keys`, also generates the keys of the current circuit or program and fails if
+/// their program ID differs from the saved one. Returns no records if the backend cannot
+/// load proofs.
+pub fn verify_saved<B: Backend>(
+    backend: &mut B,
+    dir: &Path,
+    opts: &Options,
+    check_keys: bool,
+) -> Result<Vec<Record>> {
+    let manifest = Manifest::read(dir)?;
+    let info = BackendInfo::of(backend);
+    let saved = (&manifest.task, &manifest.backend, &manifest.variant);
+    ensure!(
+        saved == (&info.task, &info.backend, &info.variant),
+        "{} holds a {}/{}/{} proof, not {}/{}/{}",
+        dir.display(),
+        manifest.task,
+        manifest.backend,
+        manifest.variant,
+        info.task,
+        info.backend,
+        info.variant
+    );
+    let file = |role: &str| match manifest.files.get(role) {
+        Some(artifact) => Ok(dir.join(&artifact.path)),
+        None => bail!(
+            "{} lists no {role} file",
+            dir.join(artifacts::MANIFEST).display()
+        ),
+    };
+    let Some(proof) = backend.load_proof(&file(artifacts::PROOF)?) else {
+        return Ok(vec![]);
+    };
+    let proof = proof?;
+
+    let size = manifest.size;
+    backend.setup(size)?;
+    if check_keys {
+        backend.keygen(size)?;
+        let program_id = backend.program_id()?;
+        ensure!(
+            program_id == manifest.program_id,
+            "the regenerated program ID {program_id} differs from the saved {}, \
+             were the backend knobs or the program changed?",
+            manifest.program_id
+        );
+    }
+    let vk = fs::read(file(artifacts::VK)?)?;
+    backend.load_verifying_key(size, &vk)?;
+    let public = fs::read(file(artifacts::PUBLIC)?)?;
+    ensure!(
+        backend.public_output(&proof)? == public,
+        "the public output of the proof differs from the saved one"
+    );
+    backend.check_output(&backend.input(size, manifest.seed), &proof)?;
+
+    for _ in 0..opts.warmups {
+        backend.verify(&proof)?;
+    }
+    let mut verify = Vec::with_capacity(opts.samples);
+    for _ in 0..opts.samples {
+        let ((), m) = measure(|| backend.verify(&proof))?;
+        verify.push(m);
+    }
+
+    let sizes = Sizes {
+        proof_bytes: Some(backend.serialize_proof(&proof)?.len()),
+        vk_bytes: Some(vk.len()),
+        public_input_bytes: Some(public.len()),
+        ..Default::default()
+    };
+    let record = |kind, peak_rss_bytes| Record {
+        sizes: sizes.clone(),
+        peak_rss_bytes,
+        program_id: Some(manifest.program_id.clone()),
+        seed: Some(manifest.seed),
+        ..info.record(size, Some(Phase::Verify), kind)
+    };
+    Ok(phase_records(record, None, &verify))
+}
+
+/// The records of one phase: its cold run if any, every sample and their summary.
+fn phase_records(
+    record: impl Fn(Kind, Option<u64>) -> Record,
+    cold: Option<Measured>,
+    measured: &[Measured],
+) -> Vec<Record> {
+    let mut records = vec![];
+    if let Some(m) = cold {
+        records.push(record(Kind::Cold { nanos: m.nanos }, m.peak_rss));
+    }
+    let nanos: Vec<u64> = measured.iter().map(|m| m.nanos).collect();
+    let outliers = stats::outliers(&nanos);
+    for (sample, (m, &outlier)) in measured.iter().zip(&outliers).enumerate() {
+        let kind = Kind::Sample {
+            sample,
+            nanos: m.nanos,
+            outlier,
+        };
+        records.push(record(kind, m.peak_rss));
+    }
+    let peak_rss = measured.iter().filter_map(|m| m.peak_rss).max();
+    records.push(record(Kind::Summary(Summary::new(&nanos)), peak_rss));
+    records
+}
+
+/// Runs [`run_cell`] for every size and writes the records to `out` as soon as each cell
+/// finishes. The artifacts of each size go into a `<size>` subdirectory of `artifacts`.
+pub fn run<B: Backend>(
+    backend: &mut B,
+    sizes: &[usize],
+    opts: &Options,
+    artifacts: Option<&Path>,
+    out: &mut Writer,
+) -> Result<()> {
+    for &size in sizes {
+        let dir = artifacts.map(|d| d.join(size.to_string()));
+        for record in run_cell(backend, size, opts, dir.as_deref())? {
+            out.write(&record)?;
+        }
+    }
+    Ok(())
+}
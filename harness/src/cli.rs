@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+
+use crate::{
+    bench::{Options, SIZES},
+    record::{BackendInfo, Writer},
+    Backend,
+};
+
+/// Arguments every task backend binary accepts. The `yazkb` runner passes these when it
+/// drives the benchmark matrix.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Input sizes to benchmark, comma separated.
+    #[arg(long, value_delimiter = ',', default_values_t = SIZES, value_parser = at_least_one)]
+    pub sizes: Vec<usize>,
+
+    #[command(flatten)]
+    pub opts: Options,
+
+    /// JSON Lines file to append the results to. Defaults to stdout.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Directory to keep the last proof of each size in, with its verifying key, public
+    /// output and a manifest, one `<size>` subdirectory per cell.
+    #[arg(long)]
+    pub artifacts: Option<PathBuf>,
+
+    /// Backend knobs as a JSON object, see [`Backend::configure`].
+    #[arg(long, default_value = "{}", value_parser = parse_json)]
+    pub params: serde_json::Value,
+
+    /// Verify the proof saved in this artifact directory instead of proving anything, see
+    /// [`crate::artifacts`].
+    #[arg(long, conflicts_with_all = ["sizes", "artifacts"])]
+    pub verify: Option<PathBuf>,
+
+    /// With `--verify`, also regenerate the keys and require their program ID to match the
+    /// saved one.
+    #[arg(long, requires = "verify")]
+    pub check_keys: bool,
+
+    /// Print the backend's identity as JSON and exit without running anything.
+    #[arg(long)]
+    pub describe: bool,
+}
+
+/// Entry point of a task backend binary: parses [`Args`] and runs every requested size.
+///
+/// Exits with a non-zero code if any cell fails.
+pub fn main<B: Backend>(backend: &mut B) {
+    let args = Args::parse();
+    let checked = backend.configure(&args.params).and_then(|()| {
+        if args.verify.is_some() {
+            return Ok(());
+        }
+        args.sizes
+            .iter()
+            .try_for_each(|&size| backend.check_size(size))
+    });
+    if let Err(e) = checked {
+        eprintln!(
+            "{}/{}: invalid params: {e:#}",
+            backend.task(),
+            backend.name()
+        );
+        std::process::exit(1);
+    }
+    if args.describe {
+        println!(
+            "{}",
+            serde_json::to_string(&BackendInfo::of(backend)).unwrap()
+        );
+        return;
+    }
+    if let Err(e) = run(backend, &args) {
+        eprintln!("{}/{} failed: {e:#}", backend.task(), backend.name());
+        std::process::exit(1);
+    }
+}
+
+fn run<B: Backend>(backend: &mut B, args: &Args) -> Result<()> {
+    let mut out = Writer::open(args.output.as_deref())?;
+    if let Some(dir) = &args.verify {
+        for record in crate::verify_saved(backend, dir, &args.opts, args.check_keys)? {
+            out.write(&record)?;
+        }
+        return Ok(());
+    }
+    crate::run(
+        backend,
+        &args.sizes,
+        &args.opts,
+        args.artifacts.as_deref(),
+        &mut out,
+    )
+}
+
+/// Parses a count that must be at least one, for clap.
+pub fn at_least_one(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("must be at least 1".into()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(format!("{e}")),
+    }
+}
+
+fn parse_json(s: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(s).map_err(|e| e.to_string())
+}
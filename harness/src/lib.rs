@@ -0,0 +1,22 @@
+//! Shared benchmark harness for the `tasks/<task>/<backend>` entry points.
+//!
+//! A backend implements [`Backend`]; [`run`] drives the warmup/prove/verify loop over the
+//! benchmark sizes and [`main`] wraps it with the command line the `yazkb` runner speaks.
+//! Every measurement is emitted as a JSON Lines [`Record`].
+
+pub mod artifacts;
+mod backend;
+mod bench;
+pub mod cli;
+pub mod corpus;
+pub mod mem;
+pub mod record;
+pub mod reference;
+mod sizes;
+pub mod stats;
+
+pub use backend::{params, Backend};
+pub use bench::{run, run_cell, verify_saved, Options, SIZES};
+pub use cli::main;
+pub use record::{BackendInfo, Environment, Kind, Phase, Record, Status};
+pub use sizes::{ByteCounter, Sizes};
@@ -0,0 +1,54 @@
+//! Peak resident set size of the current process.
+//!
+//! On Linux the kernel's high water mark (`VmHWM`) can be reset through
+//! `/proc/self/clear_refs`, so each phase gets its own peak even though all phases run in the
+//! same process. Elsewhere only `getrusage` is available, whose peak never goes down: the
+//! value of a phase is then the peak of everything run so far.
+
+use std::fs;
+
+/// Resets the peak RSS to the current RSS. Returns `false` when the platform cannot.
+pub fn reset_peak() -> bool {
+    fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+/// Peak RSS in bytes since the last successful [`reset_peak`] or process start.
+pub fn peak_rss() -> Option<u64> {
+    proc_status_kb("VmHWM")
+        .map(|kb| kb * 1024)
+        .or_else(rusage_max_rss)
+}
+
+fn proc_status_kb(key: &str) -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(unix)]
+fn rusage_max_rss() -> Option<u64> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+    // SAFETY: getrusage only writes into the provided struct.
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: initialized by the successful call above.
+    let max_rss = unsafe { usage.assume_init() }.ru_maxrss as u64;
+    // bytes on macOS, kilobytes everywhere else
+    if cfg!(target_os = "macos") {
+        Some(max_rss)
+    } else {
+        Some(max_rss * 1024)
+    }
+}
+
+#[cfg(not(unix))]
+fn rusage_max_rss() -> Option<u64> {
+    None
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{stats::Summary, Backend, Sizes};
+
+/// Timed phase of a benchmark cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Universal parameters, see [`crate::Backend::setup`].
+    Setup,
+    /// Circuit or program specific keys, see [`crate::Backend::keygen`].
+    Keygen,
+    /// Running the program without proving it, for the backends that can, see
+    /// [`crate::Backend::execute`].
+    Execute,
+    Prove,
+    Verify,
+}
+
+/// One line of a results file: a sample or the summary of one phase of a (task, backend,
+/// variant, size) cell, or the outcome of the whole cell.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub task: String,
+    pub backend: String,
+    /// Proof system flavour within the backend, e.g. `core` or `groth16` for SP1.
+    pub variant: String,
+    pub size: usize,
+    /// `None` for [`Kind::Outcome`], which covers the whole cell.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<Phase>,
+    #[serde(flatten)]
+    pub kind: Kind,
+    #[serde(flatten)]
+    pub sizes: Sizes,
+    /// Peak resident set size while the phase ran; the maximum over samples in a summary.
+    pub peak_rss_bytes: Option<u64>,
+    /// Harness and proving library versions.
+    pub versions: BTreeMap<String, String>,
+    /// See [`crate::Backend::program_id`]. `None` for [`Kind::Outcome`].
+    pub program_id: Option<String>,
+    /// Seed the task input was generated with, see [`crate::corpus`].
+    pub seed: Option<u64>,
+}
+
+/// What a [`Record`] measures, tagged as `"kind"` in the JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Kind {
+    /// A single timed run.
+    Sample {
+        sample: usize,
+        nanos: u64,
+        /// Whether the sample lies outside the Tukey fences of its phase.
+        outlier: bool,
+    },
+    /// The first round of the phase in a fresh process: the first warmup, or the first
+    /// sample when there are no warmups, which then also counts among the samples. It pays
+    /// for lazy table initialization, parameter loading and page faults.
+    Cold { nanos: u64 },
+    /// Statistics over all samples of the phase.
+    Summary(Summary),
+    /// How the process running the cell ended, written by the runner.
+    Outcome {
+        status: Status,
+        /// Error or panic message, if any.
+        message: Option<String>,
+        /// Machine and toolchain the cell ran on.
+        environment: Box<Environment>,
+        /// Directory the cell's proof, verifying key and manifest were saved in, see
+        /// [`crate::artifacts`]. A cell skipped thanks to the runner's store keeps the one of
+        /// the run that proved it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        artifacts: Option<PathBuf>,
+    },
+}
+
+/// How a benchmark cell ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    /// The backend returned an error, e.g. a proof failed to verify.
+    Error,
+    Panic,
+    /// Ran out of memory: hit the memory limit or got killed by the kernel OOM killer.
+    Oom,
+    /// Did not finish within the timeout.
+    Dnf,
+    /// Killed by any other signal.
+    Crash,
+    /// Interrupted by SIGINT or SIGTERM sent to the runner.
+    Cancelled,
+}
+
+/// Machine, toolchain and resolved dependencies a cell ran with, collected by the runner.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Environment {
+    /// CPU model name.
+    pub cpu: String,
+    /// Logical cores.
+    pub cores: usize,
+    pub memory_bytes: Option<u64>,
+    /// Operating system and architecture, e.g. `linux-x86_64`.
+    pub os: String,
+    /// `rustc -V` of the toolchain the backend is built with.
+    pub rustc: String,
+    /// Active rustup toolchain, if rustup is used.
+    pub toolchain: Option<String>,
+    /// Proving library crates resolved in the backend's `Cargo.lock`, mapped to their git
+    /// revision or, for crates.io releases, their version.
+    pub dependencies: BTreeMap<String, String>,
+}
+
+/// What identifies a backend binary in the results, printed by its `--describe` flag.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackendInfo {
+    pub task: String,
+    pub backend: String,
+    pub variant: String,
+    /// Harness and proving library versions.
+    pub versions: BTreeMap<String, String>,
+}
+
+impl BackendInfo {
+    pub fn of<B: Backend>(backend: &B) -> Self {
+        let mut versions: BTreeMap<_, _> = backend
+            .versions()
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        versions.insert("harness".into(), env!("CARGO_PKG_VERSION").into());
+        Self {
+            task: backend.task().to_string(),
+            backend: backend.name().to_string(),
+            variant: backend.variant().to_string(),
+            versions,
+        }
+    }
+
+    /// The record of a cell of this backend.
+    pub fn record(&self, size: usize, phase: Option<Phase>, kind: Kind) -> Record {
+        Record {
+            task: self.task.clone(),
+            backend: self.backend.clone(),
+            variant: self.variant.clone(),
+            size,
+            phase,
+            kind,
+            sizes: Sizes::default(),
+            peak_rss_bytes: None,
+            versions: self.versions.clone(),
+            program_id: None,
+            seed: None,
+        }
+    }
+}
+
+/// Appends [`Record`]s as JSON Lines to a file or stdout.
+pub struct Writer {
+    out: Box<dyn Write>,
+}
+
+impl Writer {
+    /// Appends to `path`, creating it if needed. Writes to stdout when `path` is `None`.
+    ///
+    /// A line left unterminated by a process killed in the middle of a write is ended first,
+    /// so the records appended after it stay readable.
+    pub fn open(path: Option<&Path>) -> Result<Self> {
+        let out: Box<dyn Write> = match path {
+            Some(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .read(true)
+                    .open(path)
+                    .with_context(|| format!("cannot open {}", path.display()))?;
+                if ends_mid_line(&mut file)? {
+                    file.write_all(b"\n")?;
+                }
+                Box::new(file)
+            }
+            None => Box::new(io::stdout()),
+        };
+        Ok(Self { out })
+    }
+
+    /// Writes one record and flushes it, so a crash never loses finished measurements.
+    ///
+    /// The line goes out in a single write, so a killed process cannot leave half of it.
+    pub fn write(&mut self, record: &Record) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.out.write_all(&line)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+fn ends_mid_line(file: &mut File) -> io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] != b'\n')
+}
+
+/// Reads every record of a JSON Lines results file, see [`parse`].
+pub fn read(path: &Path) -> Result<Vec<Record>> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("cannot open {}", path.display()))?;
+    parse(&text, &path.display().to_string())
+}
+
+/// Parses JSON Lines records read from `origin`. A record cut short by a cell killed while
+/// writing it is skipped with a warning, any other invalid line is an error.
+pub fn parse(text: &str, origin: &str) -> Result<Vec<Record>> {
+    let mut records = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(e) if e.is_eof() => {
+                eprintln!("warning: {origin}:{}: skipping a truncated record", i + 1);
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("{origin}:{}: invalid record", i + 1));
+            }
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(nanos: u64) -> Record {
+        let info = BackendInfo {
+            task: "fibo".into(),
+            backend: "native".into(),
+            variant: "rust".into(),
+            versions: BTreeMap::new(),
+        };
+        info.record(10, Some(Phase::Prove), Kind::Cold { nanos })
+    }
+
+    #[test]
+    fn skips_truncated_records() {
+        let line = serde_json::to_string(&record(1)).unwrap();
+        let text = format!("{line}\n{}\n{line}\n", &line[..line.len() / 2]);
+        assert_eq!(parse(&text, "results").unwrap(), [record(1), record(1)]);
+        assert!(parse("{\"task\": 1}\n", "results").is_err());
+    }
+
+    #[test]
+    fn ends_a_torn_line_before_appending() {
+        let path = std::env::temp_dir().join(format!("yazkb-torn-{}.jsonl", std::process::id()));
+        let line = serde_json::to_string(&record(1)).unwrap();
+        fs::write(&path, &line[..line.len() / 2]).unwrap();
+        Writer::open(Some(&path))
+            .unwrap()
+            .write(&record(2))
+            .unwrap();
+        let records = read(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(records.unwrap(), [record(2)]);
+    }
+}
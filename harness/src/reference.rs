@@ -0,0 +1,52 @@
+//! Native reference implementations of the tasks, the ground truth every backend's public
+//! output is checked against in [`crate::Backend::check_output`].
+
+use std::fmt::Debug;
+
+use anyhow::{ensure, Result};
+use sha2::{Digest, Sha256};
+
+/// The fibo task: the `n`th Fibonacci number modulo 2^64, with `F(0) = 0` and `F(1) = 1`,
+/// after exactly `n` additions. Every backend computes it this way and makes `n` and `F(n)`
+/// its public output.
+pub fn fibonacci(n: u32) -> u64 {
+    let (mut a, mut b) = (0u64, 1u64);
+    for _ in 0..n {
+        (a, b) = (b, a.wrapping_add(b));
+    }
+    a
+}
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// Fails with both values if a public output differs from the reference.
+pub fn check<T: PartialEq + Debug>(what: &str, expected: T, actual: T) -> Result<()> {
+    ensure!(
+        expected == actual,
+        "public output mismatch: expected {what} {expected:?}, got {actual:?}"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_values() {
+        let fib = fibonacci;
+        assert_eq!((fib(0), fib(1), fib(2), fib(21)), (0, 1, 1, 10946));
+        // F(93) is the largest that fits, F(94) wraps
+        assert_eq!(fib(93), 12200160415121876738);
+        assert_eq!(fib(94), fib(93).wrapping_add(fib(92)));
+        assert_eq!(fib(94), 1293530146158671551);
+        assert_eq!(
+            sha256(b"abc"),
+            *b"\xba\x78\x16\xbf\x8f\x01\xcf\xea\x41\x41\x40\xde\x5d\xae\x22\x23\
+               \xb0\x03\x61\xa3\x96\x17\x7a\x9c\xb4\x10\xff\x61\xf2\x00\x15\xad"
+        );
+        assert!(check("n", 1, 2).is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+/// Serialized sizes of what a cell produces, in bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sizes {
+    pub proof_bytes: Option<usize>,
+    pub vk_bytes: Option<usize>,
+    /// `None` when the backend has no program-specific proving key or cannot serialize it.
+    pub pk_bytes: Option<usize>,
+    /// Public inputs/outputs the verifier checks the proof against.
+    pub public_input_bytes: Option<usize>,
+}
+
+/// [`io::Write`] sink that only counts bytes, to size keys too big to buffer.
+#[derive(Debug, Default)]
+pub struct ByteCounter(pub usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+
+/// Summary statistics of the timed samples of one phase, in nanoseconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub samples: usize,
+    pub median: f64,
+    pub mean: f64,
+    /// Sample standard deviation, 0 with a single sample.
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub p95: f64,
+    /// Number of samples outside the Tukey fences, see [`outliers`].
+    pub outliers: usize,
+}
+
+impl Summary {
+    /// Summarizes `samples`, which must not be empty.
+    pub fn new(samples: &[u64]) -> Self {
+        assert!(!samples.is_empty(), "no samples to summarize");
+        let sorted = sorted(samples);
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / n;
+        let stddev = if sorted.len() > 1 {
+            (sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        Self {
+            samples: sorted.len(),
+            median: percentile(&sorted, 50.0),
+            mean,
+            stddev,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            p95: percentile(&sorted, 95.0),
+            outliers: outliers(samples).iter().filter(|&&o| o).count(),
+        }
+    }
+}
+
+/// Flags the samples outside the Tukey fences `[q1 - 1.5 * iqr, q3 + 1.5 * iqr]`.
+///
+/// Fewer than four samples are too few to tell, so nothing is flagged.
+pub fn outliers(samples: &[u64]) -> Vec<bool> {
+    if samples.len() < 4 {
+        return vec![false; samples.len()];
+    }
+    let sorted = sorted(samples);
+    let (q1, q3) = (percentile(&sorted, 25.0), percentile(&sorted, 75.0));
+    let iqr = q3 - q1;
+    let (lo, hi) = (q1 - 1.5 * iqr, q3 + 1.5 * iqr);
+    samples
+        .iter()
+        .map(|&x| (x as f64) < lo || (x as f64) > hi)
+        .collect()
+}
+
+/// Linearly interpolated percentile of an ascending, non-empty slice.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+}
+
+/// Two-sided p-value of the Mann-Whitney U test that `a` and `b` come from the same
+/// distribution. Both must be non-empty.
+///
+/// Exact for small samples without ties, normal approximation with tie correction otherwise.
+/// With three samples per side the smallest possible p-value is 0.1, so detecting changes
+/// at the usual 0.05 level needs at least four or five samples.
+pub fn mann_whitney(a: &[u64], b: &[u64]) -> f64 {
+    assert!(!a.is_empty() && !b.is_empty(), "no samples to compare");
+    let (m, n) = (a.len(), b.len());
+    let mut all: Vec<(u64, bool)> = a
+        .iter()
+        .map(|&x| (x, true))
+        .chain(b.iter().map(|&x| (x, false)))
+        .collect();
+    all.sort_unstable();
+
+    // midranks, 1-based, and the tie correction term sum(t^3 - t)
+    let (mut rank_sum, mut ties) = (0.0, 0.0);
+    let mut i = 0;
+    while i < all.len() {
+        let j = i + all[i..].iter().take_while(|(x, _)| *x == all[i].0).count();
+        let rank = (i + j + 1) as f64 / 2.0;
+        rank_sum += rank * all[i..j].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        let t = (j - i) as f64;
+        ties += t * t * t - t;
+        i = j;
+    }
+    let u = rank_sum - (m * (m + 1)) as f64 / 2.0;
+
+    let p = if ties == 0.0 && m + n <= 40 {
+        let counts = u_counts(m, n);
+        let total: f64 = counts.iter().sum();
+        let u = u as usize;
+        let lower: f64 = counts[..=u].iter().sum();
+        let upper: f64 = counts[u..].iter().sum();
+        2.0 * lower.min(upper) / total
+    } else {
+        let (m, n) = (m as f64, n as f64);
+        let mean = m * n / 2.0;
+        let var = m * n / 12.0 * ((m + n + 1.0) - ties / ((m + n) * (m + n - 1.0)));
+        if var == 0.0 {
+            return 1.0;
+        }
+        // continuity correction
+        let z = ((u - mean).abs() - 0.5).max(0.0) / var.sqrt();
+        2.0 * (1.0 - normal_cdf(z))
+    };
+    p.min(1.0)
+}
+
+/// Number of arrangements of `m` and `n` distinct samples giving each value of U.
+fn u_counts(m: usize, n: usize) -> Vec<f64> {
+    // counts[i][j][u] over i of the first sample and j of the second, built up by
+    // appending the largest element to either side
+    let mut counts = vec![vec![vec![]; n + 1]; m + 1];
+    for i in 0..=m {
+        for j in 0..=n {
+            let mut c = vec![0.0; i * j + 1];
+            if i == 0 || j == 0 {
+                c[0] = 1.0;
+            } else {
+                // the largest element in the first sample beats all j of the second
+                for (u, x) in counts[i - 1][j].iter().enumerate() {
+                    c[u + j] += x;
+                }
+                for (u, x) in counts[i][j - 1].iter().enumerate() {
+                    c[u] += x;
+                }
+            }
+            counts[i][j] = c;
+        }
+    }
+    counts.swap_remove(m).swap_remove(n)
+}
+
+/// Standard normal CDF, using the Abramowitz and Stegun 7.1.26 approximation of erf.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / 2f64.sqrt();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+fn sorted(samples: &[u64]) -> Vec<f64> {
+    let mut sorted: Vec<f64> = samples.iter().map(|&x| x as f64).collect();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary() {
+        let s = Summary::new(&[4, 1, 3, 2, 5]);
+        assert_eq!(s.samples, 5);
+        assert_eq!(s.median, 3.0);
+        assert_eq!(s.mean, 3.0);
+        assert!((s.stddev - 2.5f64.sqrt()).abs() < 1e-9);
+        assert_eq!((s.min, s.max), (1.0, 5.0));
+        assert!((s.p95 - 4.8).abs() < 1e-9);
+        assert_eq!(s.outliers, 0);
+    }
+
+    #[test]
+    fn single_sample() {
+        let s = Summary::new(&[7]);
+        assert_eq!((s.median, s.mean, s.stddev, s.p95), (7.0, 7.0, 0.0, 7.0));
+    }
+
+    #[test]
+    fn flags_outliers() {
+        let flags = outliers(&[10, 11, 10, 12, 11, 100]);
+        assert_eq!(flags, [false, false, false, false, false, true]);
+        assert_eq!(outliers(&[1, 100, 1]), [false; 3]);
+    }
+
+    #[test]
+    fn mann_whitney_p_values() {
+        // completely separated 3 vs 3: 2 of the 20 arrangements are as extreme
+        assert!((mann_whitney(&[1, 2, 3], &[4, 5, 6]) - 0.1).abs() < 1e-9);
+        assert!((mann_whitney(&[4, 5, 6], &[1, 2, 3]) - 0.1).abs() < 1e-9);
+        assert!((mann_whitney(&[1, 2, 3, 4, 5], &[6, 7, 8, 9, 10]) - 2.0 / 252.0).abs() < 1e-9);
+        assert_eq!(mann_whitney(&[1, 4, 5], &[2, 3, 6]), 1.0);
+        // ties go through the normal approximation
+        assert_eq!(mann_whitney(&[5, 5, 5], &[5, 5, 5]), 1.0);
+        assert!(mann_whitney(&[1; 30], &[2; 30]) < 1e-6);
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use crate::discover::Target;
+
+/// Builds `target` in release mode and returns the path of its benchmark binary, the one
+/// built from `src/main.rs`.
+pub fn build(target: &Target) -> Result<PathBuf> {
+    let mut child = Command::new("cargo")
+        .args([
+            "build",
+            "--release",
+            "--message-format=json-render-diagnostics",
+        ])
+        .arg("--manifest-path")
+        .arg(&target.manifest)
+        .current_dir(target.crate_dir())
+        // let each backend pick its own toolchain from rust-toolchain.toml
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("cannot spawn cargo")?;
+
+    let crate_dir = target.crate_dir().canonicalize()?;
+    let mut executable = None;
+    for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+        let msg: Value = match serde_json::from_str(&line?) {
+            Ok(msg) => msg,
+            Err(_) => continue,
+        };
+        if msg["reason"] != "compiler-artifact" {
+            continue;
+        }
+        let (Some(exe), Some(src)) = (
+            msg["executable"].as_str(),
+            msg["target"]["src_path"].as_str(),
+        ) else {
+            continue;
+        };
+        let src = PathBuf::from(src);
+        if src.ends_with("src/main.rs") && src.starts_with(&crate_dir) {
+            executable = Some(PathBuf::from(exe));
+        }
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("cargo build failed with {status}");
+    }
+    executable.with_context(|| {
+        format!(
+            "no src/main.rs binary built for {}",
+            target.manifest.display()
+        )
+    })
+}
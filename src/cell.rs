@@ -0,0 +1,333 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader},
+    mem::MaybeUninit,
+    os::unix::process::CommandExt,
+    path::Path,
+    process::{ChildStderr, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use harness::{BackendInfo, Status};
+
+use crate::interrupt;
+
+/// Lines of stderr kept to explain a failed cell.
+const STDERR_TAIL: usize = 64;
+
+/// Resource limits of the process running one cell.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub timeout: Duration,
+    /// Address space limit in bytes.
+    pub memory: Option<u64>,
+}
+
+/// How the process running a cell ended.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    pub status: Status,
+    pub message: Option<String>,
+    /// Peak RSS of the whole process, as reported by the kernel.
+    pub peak_rss: Option<u64>,
+}
+
+/// Asks a built backend binary who it is. This also has the backend check its `params`
+/// against the `sizes` it will run.
+pub fn describe(exe: &Path, dir: &Path, params: &str, sizes: &[usize]) -> Result<BackendInfo> {
+    let sizes: Vec<String> = sizes.iter().map(usize::to_string).collect();
+    let out = Command::new(exe)
+        .args([
+            "--describe",
+            "--params",
+            params,
+            "--sizes",
+            &sizes.join(","),
+        ])
+        .current_dir(dir)
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("cannot run {}", exe.display()))?;
+    if !out.status.success() {
+        bail!("{} --describe failed with {}", exe.display(), out.status);
+    }
+    // loggers may print before the description, which is the last line
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let line = stdout.lines().last().unwrap_or_default();
+    serde_json::from_str(line).with_context(|| format!("invalid description `{line}`"))
+}
+
+/// Runs `exe` with `args` from `dir` under `limits`, echoing its stderr, and classifies how
+/// it ended. Kills it when the runner is interrupted.
+pub fn run(exe: &Path, args: &[String], dir: &Path, limits: Limits) -> Result<Outcome> {
+    let mut cmd = Command::new(exe);
+    cmd.args(args).current_dir(dir).stderr(Stdio::piped());
+    if let Some(bytes) = limits.memory {
+        // SAFETY: setrlimit is async-signal-safe and nothing else runs before exec.
+        unsafe {
+            cmd.pre_exec(move || {
+                let limit = libc::rlimit {
+                    rlim_cur: bytes as _,
+                    rlim_max: bytes as _,
+                };
+                if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("cannot spawn {}", exe.display()))?;
+    let stderr = child.stderr.take().unwrap();
+    let tail = thread::spawn(move || tee(stderr));
+
+    let deadline = Instant::now() + limits.timeout;
+    let mut timed_out = false;
+    let mut cancelled = false;
+    let (status, usage) = loop {
+        if let Some(done) = try_wait(child.id() as _)? {
+            break done;
+        }
+        if !timed_out && Instant::now() >= deadline {
+            child.kill()?;
+            timed_out = true;
+        }
+        if !cancelled && interrupt::requested() {
+            // a Ctrl-C reaches the child too, a SIGTERM only the runner
+            child.kill()?;
+            cancelled = true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+    let tail = tail.join().unwrap();
+
+    // bytes on macOS, kilobytes everywhere else
+    let max_rss = usage.ru_maxrss as u64;
+    let peak_rss = Some(if cfg!(target_os = "macos") {
+        max_rss
+    } else {
+        max_rss * 1024
+    });
+    // the child may have died of the same SIGINT before the loop noticed
+    let cancelled = cancelled || interrupt::requested();
+    let (status, message) = classify(status, timed_out, cancelled, limits, &tail);
+    Ok(Outcome {
+        status,
+        message,
+        peak_rss,
+    })
+}
+
+fn classify(
+    status: libc::c_int,
+    timed_out: bool,
+    cancelled: bool,
+    limits: Limits,
+    stderr: &[String],
+) -> (Status, Option<String>) {
+    if timed_out {
+        let msg = format!("did not finish within {:?}", limits.timeout);
+        return (Status::Dnf, Some(msg));
+    }
+    if libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0 {
+        return (Status::Ok, None);
+    }
+    if cancelled {
+        return (Status::Cancelled, Some("interrupted".into()));
+    }
+    // the message Rust prints before aborting on a failed allocation
+    if let Some(line) = stderr
+        .iter()
+        .find(|l| l.contains("memory allocation of") && l.contains("failed"))
+    {
+        return (Status::Oom, Some(line.clone()));
+    }
+    if libc::WIFSIGNALED(status) {
+        return match libc::WTERMSIG(status) {
+            libc::SIGKILL => {
+                let msg = "killed by SIGKILL, most likely by the OOM killer";
+                (Status::Oom, Some(msg.into()))
+            }
+            sig => (Status::Crash, Some(format!("killed by signal {sig}"))),
+        };
+    }
+    // exit code of a Rust panic
+    if libc::WEXITSTATUS(status) == 101 {
+        let msg = stderr
+            .iter()
+            .position(|l| l.contains("panicked at"))
+            .map(|i| stderr[i..stderr.len().min(i + 2)].join("\n"));
+        return (Status::Panic, msg);
+    }
+    let msg = stderr.iter().rev().find(|l| !l.trim().is_empty()).cloned();
+    (Status::Error, msg)
+}
+
+/// Echoes the child's stderr and returns its last lines.
+fn tee(stderr: ChildStderr) -> Vec<String> {
+    let mut tail = VecDeque::with_capacity(STDERR_TAIL);
+    let mut reader = BufReader::new(stderr);
+    let mut buf = vec![];
+    while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+        if n == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+        eprintln!("{line}");
+        if tail.len() == STDERR_TAIL {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+        buf.clear();
+    }
+    tail.into()
+}
+
+/// Non-blocking `wait4`, which unlike `Child::try_wait` also reports resource usage.
+fn try_wait(pid: libc::pid_t) -> io::Result<Option<(libc::c_int, libc::rusage)>> {
+    let mut status = 0;
+    let mut usage = MaybeUninit::<libc::rusage>::zeroed();
+    // SAFETY: wait4 only writes into the provided status and rusage.
+    match unsafe { libc::wait4(pid, &mut status, libc::WNOHANG, usage.as_mut_ptr()) } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(None),
+        // SAFETY: filled in since the child was reaped.
+        _ => Ok(Some((status, unsafe { usage.assume_init() }))),
+    }
+}
+
+/// Parses a duration such as `90s`, `30m`, `2h`, or plain seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (num, unit) = split_unit(s);
+    let secs: u64 = num
+        .parse()
+        .map_err(|e| format!("invalid duration `{s}`: {e}"))?;
+    let mult = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => return Err(format!("invalid duration unit `{unit}`, use s, m or h")),
+    };
+    let secs = secs
+        .checked_mul(mult)
+        .ok_or_else(|| format!("duration `{s}` is too long"))?;
+    Ok(Duration::from_secs(secs))
+}
+
+/// Parses a size such as `512M`, `16G`, or plain bytes.
+pub fn parse_bytes(s: &str) -> Result<u64, String> {
+    let (num, unit) = split_unit(s);
+    let n: u64 = num
+        .parse()
+        .map_err(|e| format!("invalid size `{s}`: {e}"))?;
+    let shift = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("invalid size unit `{unit}`, use K, M, G or T")),
+    };
+    n.checked_mul(1 << shift)
+        .ok_or_else(|| format!("size `{s}` does not fit in 64 bits"))
+}
+
+fn split_unit(s: &str) -> (&str, &str) {
+    let s = s.trim();
+    let i = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        timeout: Duration::from_secs(60),
+        memory: None,
+    };
+
+    /// Raw wait statuses of an exit and of a death by signal.
+    fn exited(code: libc::c_int) -> libc::c_int {
+        code << 8
+    }
+
+    fn signaled(sig: libc::c_int) -> libc::c_int {
+        sig
+    }
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn classifies_exits() {
+        let run = |status, stderr: &[&str]| classify(status, false, false, LIMITS, &lines(stderr));
+        assert_eq!(run(exited(0), &["warning"]), (Status::Ok, None));
+        let panic = [
+            "thread 'main' panicked at src/main.rs:3:5:",
+            "index out of bounds",
+            "note: run with `RUST_BACKTRACE=1`",
+        ];
+        let (status, msg) = run(exited(101), &panic);
+        assert_eq!(status, Status::Panic);
+        assert_eq!(msg.unwrap(), panic[..2].join("\n"));
+        let oom = ["memory allocation of 1073741824 bytes failed"];
+        assert_eq!(
+            run(signaled(libc::SIGABRT), &oom),
+            (Status::Oom, Some(oom[0].into()))
+        );
+        assert_eq!(run(signaled(libc::SIGKILL), &[]).0, Status::Oom);
+        let (status, msg) = run(signaled(libc::SIGSEGV), &[]);
+        assert_eq!(status, Status::Crash);
+        assert_eq!(msg.unwrap(), format!("killed by signal {}", libc::SIGSEGV));
+        let error = ["Error: no such file", "", "  "];
+        assert_eq!(
+            run(exited(1), &error),
+            (Status::Error, Some(error[0].into()))
+        );
+    }
+
+    #[test]
+    fn timeouts_and_interrupts_win() {
+        let (status, msg) = classify(signaled(libc::SIGKILL), true, true, LIMITS, &[]);
+        assert_eq!(status, Status::Dnf);
+        assert_eq!(msg.unwrap(), "did not finish within 60s");
+        let status = classify(signaled(libc::SIGINT), false, true, LIMITS, &[]);
+        assert_eq!(status, (Status::Cancelled, Some("interrupted".into())));
+        // a run that finished before the interrupt still counts
+        assert_eq!(
+            classify(exited(0), false, true, LIMITS, &[]),
+            (Status::Ok, None)
+        );
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 30m "), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert!(parse_duration("2d").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration(&format!("{}h", u64::MAX / 60)).is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_bytes("512"), Ok(512));
+        assert_eq!(parse_bytes("1k"), Ok(1024));
+        assert_eq!(parse_bytes("512M"), Ok(512 << 20));
+        assert_eq!(parse_bytes("16G"), Ok(16 << 30));
+        assert_eq!(parse_bytes("2T"), Ok(2 << 40));
+        assert!(parse_bytes("16GB").is_err());
+        assert!(parse_bytes("G").is_err());
+        assert!(parse_bytes("16777216T").is_err());
+        assert!(parse_bytes(&format!("{}", u128::from(u64::MAX) + 1)).is_err());
+    }
+}
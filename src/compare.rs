@@ -0,0 +1,179 @@
+//! Compares a new results file against a baseline, cell by cell.
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, Result};
+use harness::{stats, Kind, Phase, Record};
+
+/// What identifies the samples of one phase across results files.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    task: String,
+    backend: String,
+    variant: String,
+    size: usize,
+    phase: Phase,
+}
+
+/// How a phase changed between the baseline and the new results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Verdict {
+    Regression,
+    Improvement,
+    /// The change is not statistically significant or below the threshold.
+    Unchanged,
+}
+
+/// Prints the change of every phase present in both files. Returns `false` if any phase got
+/// significantly slower, at level `alpha`, by more than `threshold` percent of its median.
+pub fn compare(baseline: &Path, new: &Path, threshold: f64, alpha: f64) -> Result<bool> {
+    let old = samples(&harness::record::read(baseline)?);
+    let new = samples(&harness::record::read(new)?);
+    if !old.keys().any(|k| new.contains_key(k)) {
+        bail!("the results files have no cell in common");
+    }
+
+    let mut regressions = 0;
+    for (key, after) in &new {
+        let name = format!(
+            "{}/{}/{}\t{}\t{:?}",
+            key.task, key.backend, key.variant, key.size, key.phase
+        );
+        let Some(before) = old.get(key) else {
+            println!("{name}\tnot in baseline");
+            continue;
+        };
+        let (m0, m1) = (median(before), median(after));
+        let (change, p, verdict) = verdict(before, after, threshold, alpha);
+        if verdict == Verdict::Regression {
+            regressions += 1;
+        }
+        println!(
+            "{name}\t{:.3} ms -> {:.3} ms\t{change:+.1}%\tp={p:.3}\t{verdict:?}",
+            m0 / 1e6,
+            m1 / 1e6,
+        );
+    }
+    for key in old.keys().filter(|k| !new.contains_key(k)) {
+        println!(
+            "{}/{}/{}\t{}\t{:?}\tnot in new results",
+            key.task, key.backend, key.variant, key.size, key.phase
+        );
+    }
+
+    if regressions > 0 {
+        println!("==> {regressions} regression(s) above {threshold}%");
+    }
+    Ok(regressions == 0)
+}
+
+/// The change of the median in percent, the p-value of the change and the verdict.
+fn verdict(before: &[u64], after: &[u64], threshold: f64, alpha: f64) -> (f64, f64, Verdict) {
+    let (m0, m1) = (median(before), median(after));
+    let change = (m1 - m0) / m0.max(1.0) * 100.0;
+    let p = stats::mann_whitney(before, after);
+    let verdict = if p >= alpha || change.abs() <= threshold {
+        Verdict::Unchanged
+    } else if change > 0.0 {
+        Verdict::Regression
+    } else {
+        Verdict::Improvement
+    };
+    (change, p, verdict)
+}
+
+/// Timed samples grouped by phase. Samples of repeated runs of a cell are pooled.
+fn samples(records: &[Record]) -> BTreeMap<Key, Vec<u64>> {
+    let mut samples = BTreeMap::<_, Vec<_>>::new();
+    for r in records {
+        let (Kind::Sample { nanos, .. }, Some(phase)) = (&r.kind, r.phase) else {
+            continue;
+        };
+        let key = Key {
+            task: r.task.clone(),
+            backend: r.backend.clone(),
+            variant: r.variant.clone(),
+            size: r.size,
+            phase,
+        };
+        samples.entry(key).or_default().push(*nanos);
+    }
+    samples
+}
+
+fn median(samples: &[u64]) -> f64 {
+    stats::Summary::new(samples).median
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs};
+
+    use harness::{record::Writer, BackendInfo};
+
+    use super::*;
+
+    /// Ten samples around `ms` milliseconds.
+    fn around(ms: u64) -> Vec<u64> {
+        (0..10).map(|i| (ms * 1000 + i * 7) * 1000).collect()
+    }
+
+    #[test]
+    fn verdicts() {
+        let (change, p, verdict) = verdict(&around(100), &around(150), 5.0, 0.05);
+        assert_eq!(verdict, Verdict::Regression, "{change}% p={p}");
+        assert_eq!(verdict_of(&around(150), &around(100)), Verdict::Improvement);
+        // the same samples
+        assert_eq!(verdict_of(&around(100), &around(100)), Verdict::Unchanged);
+        // significant, but below the threshold
+        assert_eq!(verdict_of(&around(100), &around(102)), Verdict::Unchanged);
+        // above the threshold, but three samples cannot be significant at 0.05
+        assert_eq!(
+            verdict_of(&around(100)[..3], &around(150)[..3]),
+            Verdict::Unchanged
+        );
+    }
+
+    fn verdict_of(before: &[u64], after: &[u64]) -> Verdict {
+        verdict(before, after, 5.0, 0.05).2
+    }
+
+    /// A results file with the prove samples of one cell.
+    fn results(name: &str, samples: &[u64]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("yazkb-{name}-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let info = BackendInfo {
+            task: "fibo".into(),
+            backend: "native".into(),
+            variant: "rust".into(),
+            versions: BTreeMap::new(),
+        };
+        let mut out = Writer::open(Some(&path)).unwrap();
+        for (sample, &nanos) in samples.iter().enumerate() {
+            let kind = Kind::Sample {
+                sample,
+                nanos,
+                outlier: false,
+            };
+            out.write(&info.record(10, Some(Phase::Prove), kind))
+                .unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn fails_on_a_regression_only() {
+        let baseline = results("baseline", &around(100));
+        let slower = results("slower", &around(150));
+        let faster = results("faster", &around(50));
+        let regressed = compare(&baseline, &slower, 5.0, 0.05).unwrap();
+        let improved = compare(&baseline, &faster, 5.0, 0.05).unwrap();
+        let unchanged = compare(&baseline, &baseline, 5.0, 0.05).unwrap();
+        for path in [baseline, slower, faster] {
+            fs::remove_file(path).unwrap();
+        }
+        assert!(!regressed);
+        assert!(improved);
+        assert!(unchanged);
+    }
+}
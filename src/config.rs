@@ -0,0 +1,290 @@
+//! The declarative benchmark configuration, `yazkb.toml`.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{cell, discover::Target};
+
+/// The whole configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Settings of every task that does not override them.
+    #[serde(default)]
+    pub defaults: Settings,
+    #[serde(default)]
+    pub tasks: BTreeMap<String, TaskConfig>,
+}
+
+/// Sizes, repetitions and limits, all optional so tasks can override only some of them.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub sizes: Option<Vec<usize>>,
+    pub warmups: Option<usize>,
+    pub samples: Option<usize>,
+    /// Seed of the generated task inputs.
+    pub seed: Option<u64>,
+    /// Wall-clock limit of each cell, e.g. `"90s"`, `"30m"` or `"2h"`.
+    pub timeout: Option<String>,
+    /// Address space limit of each cell, e.g. `"16G"`.
+    pub memory_limit: Option<String>,
+}
+
+/// A `[tasks.<task>]` table: [`Settings`] overriding the defaults, plus backend knobs.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskConfig {
+    pub sizes: Option<Vec<usize>>,
+    pub warmups: Option<usize>,
+    pub samples: Option<usize>,
+    pub seed: Option<u64>,
+    pub timeout: Option<String>,
+    pub memory_limit: Option<String>,
+    /// Knobs of each backend, handed to [`harness::Backend::configure`] as they are.
+    #[serde(default)]
+    pub backends: BTreeMap<String, toml::Table>,
+}
+
+/// Everything needed to run the cells of one target.
+#[derive(Clone, Debug)]
+pub struct Resolved {
+    pub sizes: Vec<usize>,
+    pub opts: harness::Options,
+    pub limits: cell::Limits,
+    /// Backend knobs as JSON, passed with `--params`.
+    pub params: serde_json::Value,
+}
+
+impl Config {
+    /// Reads and validates `path` against the discovered `targets`.
+    pub fn load(path: &Path, targets: &[Target]) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+        let config: Config =
+            toml::from_str(&text).with_context(|| format!("invalid {}", path.display()))?;
+        config
+            .validate(targets)
+            .with_context(|| format!("invalid {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Checks everything that can be checked before building anything, reporting every
+    /// problem at once.
+    pub fn validate(&self, targets: &[Target]) -> Result<()> {
+        let mut errors = vec![];
+        errors.extend(self.defaults.errors("defaults"));
+        for (task, config) in &self.tasks {
+            let known: Vec<&str> = targets
+                .iter()
+                .filter(|t| &t.task == task)
+                .map(|t| t.backend.as_str())
+                .collect();
+            if known.is_empty() {
+                errors.push(format!(
+                    "tasks.{task}: unknown task, expected one of: {}",
+                    join(targets.iter().map(|t| t.task.as_str()))
+                ));
+                continue;
+            }
+            errors.extend(config.settings().errors(&format!("tasks.{task}")));
+            for backend in config.backends.keys() {
+                if !known.contains(&backend.as_str()) {
+                    errors.push(format!(
+                        "tasks.{task}.backends.{backend}: unknown backend, expected one of: {}",
+                        join(known.iter().copied())
+                    ));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            bail!("{}", errors.join("\n"));
+        }
+        Ok(())
+    }
+
+    /// The settings of `target`: the task's, falling back to the defaults and then to the
+    /// built-in defaults. Only call on a validated config.
+    pub fn resolve(&self, target: &Target) -> Result<Resolved> {
+        let task = self.tasks.get(&target.task);
+        let settings = match task {
+            Some(task) => task.settings().or(&self.defaults),
+            None => self.defaults.clone(),
+        };
+        let defaults = harness::Options::default();
+        let timeout = settings.timeout.as_deref().unwrap_or("1h");
+        let params = task
+            .and_then(|t| t.backends.get(&target.backend))
+            .cloned()
+            .unwrap_or_default();
+        Ok(Resolved {
+            sizes: settings.sizes.unwrap_or_else(|| harness::SIZES.to_vec()),
+            opts: harness::Options {
+                warmups: settings.warmups.unwrap_or(defaults.warmups),
+                samples: settings.samples.unwrap_or(defaults.samples),
+                seed: settings.seed.unwrap_or(defaults.seed),
+            },
+            limits: cell::Limits {
+                timeout: cell::parse_duration(timeout).map_err(anyhow::Error::msg)?,
+                memory: settings
+                    .memory_limit
+                    .as_deref()
+                    .map(cell::parse_bytes)
+                    .transpose()
+                    .map_err(anyhow::Error::msg)?,
+            },
+            params: serde_json::to_value(params)?,
+        })
+    }
+}
+
+impl TaskConfig {
+    fn settings(&self) -> Settings {
+        Settings {
+            sizes: self.sizes.clone(),
+            warmups: self.warmups,
+            samples: self.samples,
+            seed: self.seed,
+            timeout: self.timeout.clone(),
+            memory_limit: self.memory_limit.clone(),
+        }
+    }
+}
+
+impl Settings {
+    /// These settings, with the unset ones taken from `fallback`.
+    fn or(self, fallback: &Settings) -> Settings {
+        let fallback = fallback.clone();
+        Settings {
+            sizes: self.sizes.or(fallback.sizes),
+            warmups: self.warmups.or(fallback.warmups),
+            samples: self.samples.or(fallback.samples),
+            seed: self.seed.or(fallback.seed),
+            timeout: self.timeout.or(fallback.timeout),
+            memory_limit: self.memory_limit.or(fallback.memory_limit),
+        }
+    }
+
+    fn errors(&self, at: &str) -> Vec<String> {
+        let mut errors = vec![];
+        if let Some(sizes) = &self.sizes {
+            if sizes.is_empty() {
+                errors.push(format!("{at}.sizes: must not be empty"));
+            }
+            if sizes.contains(&0) {
+                errors.push(format!("{at}.sizes: sizes must be at least 1"));
+            }
+        }
+        if self.samples == Some(0) {
+            errors.push(format!("{at}.samples: must be at least 1"));
+        }
+        if let Some(Err(e)) = self.timeout.as_deref().map(cell::parse_duration) {
+            errors.push(format!("{at}.timeout: {e}"));
+        }
+        if let Some(Err(e)) = self.memory_limit.as_deref().map(cell::parse_bytes) {
+            errors.push(format!("{at}.memory_limit: {e}"));
+        }
+        errors
+    }
+}
+
+fn join<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    let mut names: Vec<_> = names.collect();
+    names.dedup();
+    names.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::*;
+
+    fn targets() -> Vec<Target> {
+        [("fibo", "halo2"), ("fibo", "native"), ("sha2", "sp1")]
+            .map(|(task, backend)| Target {
+                task: task.into(),
+                backend: backend.into(),
+                manifest: PathBuf::from(format!("tasks/{task}/{backend}/Cargo.toml")),
+            })
+            .to_vec()
+    }
+
+    fn errors(toml: &str) -> String {
+        let config: Config = toml::from_str(toml).unwrap();
+        match config.validate(&targets()) {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn accepts_a_valid_config() {
+        let config = r#"
+            [defaults]
+            sizes = [10, 100]
+            timeout = "30m"
+            memory_limit = "16G"
+
+            [tasks.fibo]
+            samples = 5
+
+            [tasks.fibo.backends.halo2]
+            rows_per_step = 4
+        "#;
+        assert_eq!(errors(config), "");
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let config = r#"
+            [defaults]
+            sizes = [0]
+            samples = 0
+            timeout = "2d"
+
+            [tasks.fibo]
+            sizes = []
+            memory_limit = "99999999999T"
+
+            [tasks.fibo.backends.risc0]
+
+            [tasks.keccak]
+        "#;
+        let errors = errors(config);
+        for expected in [
+            "defaults.sizes: sizes must be at least 1",
+            "defaults.samples: must be at least 1",
+            "defaults.timeout: invalid duration unit `d`",
+            "tasks.fibo.sizes: must not be empty",
+            "tasks.fibo.memory_limit: size `99999999999T` does not fit in 64 bits",
+            "tasks.fibo.backends.risc0: unknown backend, expected one of: halo2, native",
+            "tasks.keccak: unknown task, expected one of: fibo, sha2",
+        ] {
+            assert!(errors.contains(expected), "no `{expected}` in:\n{errors}");
+        }
+    }
+
+    #[test]
+    fn tasks_override_the_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [defaults]
+            sizes = [10]
+            samples = 5
+            timeout = "2h"
+
+            [tasks.fibo]
+            samples = 7
+            "#,
+        )
+        .unwrap();
+        let resolved = config.resolve(&targets()[0]).unwrap();
+        assert_eq!(resolved.sizes, [10]);
+        assert_eq!(resolved.opts.samples, 7);
+        assert_eq!(resolved.limits.timeout, Duration::from_secs(7200));
+        assert_eq!(resolved.limits.memory, None);
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+/// A `tasks/<task>/<backend>` directory the runner knows how to build and run.
+#[derive(Clone, Debug)]
+pub struct Target {
+    pub task: String,
+    pub backend: String,
+    /// Manifest of the crate whose `src/main.rs` is the benchmark entry point.
+    pub manifest: PathBuf,
+}
+
+impl Target {
+    /// Directory the backend binary is built and run from.
+    pub fn crate_dir(&self) -> &Path {
+        self.manifest.parent().unwrap()
+    }
+}
+
+/// Finds every `<tasks_dir>/<task>/<backend>` with a Cargo manifest, sorted by task and
+/// backend.
+///
+/// zkVM backends keep their host binary in `script/` (SP1); everything else has the manifest
+/// right in the backend directory.
+pub fn discover(tasks_dir: &Path) -> Result<Vec<Target>> {
+    let tasks_dir = tasks_dir
+        .canonicalize()
+        .with_context(|| format!("cannot find tasks directory {}", tasks_dir.display()))?;
+    let mut targets = vec![];
+    for task in subdirs(&tasks_dir)? {
+        for backend in subdirs(&task)? {
+            let manifest = ["script/Cargo.toml", "Cargo.toml"]
+                .iter()
+                .map(|m| backend.join(m))
+                .find(|m| m.is_file());
+            if let Some(manifest) = manifest {
+                targets.push(Target {
+                    task: file_name(&task),
+                    backend: file_name(&backend),
+                    manifest,
+                });
+            }
+        }
+    }
+    targets.sort_by(|a, b| (&a.task, &a.backend).cmp(&(&b.task, &b.backend)));
+    Ok(targets)
+}
+
+fn subdirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = vec![];
+    for entry in fs::read_dir(dir).with_context(|| format!("cannot read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() && !file_name(&path).starts_with('.') {
+            dirs.push(path);
+        }
+    }
+    Ok(dirs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_string_lossy().into_owned()
+}
@@ -0,0 +1,209 @@
+//! Collects the [`Environment`] a backend runs in.
+
+use std::{collections::BTreeMap, fs, path::Path, process::Command};
+
+use anyhow::{bail, Context, Result};
+use harness::Environment;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Name prefixes of the proving library crates whose resolved revisions are recorded. Only
+/// crates from a registry or git match: the task packages themselves, like the local one
+/// named `halo2`, have no source in `Cargo.lock`.
+const TRACKED: [&str; 5] = ["halo2", "snark-verifier", "zkevm-hashes", "sp1-", "risc0-"];
+
+/// CPU, memory and OS of this machine, shared by all backends.
+pub fn host() -> Environment {
+    Environment {
+        cpu: cpu_model().unwrap_or_else(|| "unknown".into()),
+        cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        memory_bytes: memory_bytes(),
+        os: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        ..Default::default()
+    }
+}
+
+/// `host` completed with the toolchain and `Cargo.lock` of the backend built in `dir`.
+pub fn backend(host: &Environment, dir: &Path) -> Result<Environment> {
+    Ok(Environment {
+        rustc: rustc(dir)?,
+        toolchain: toolchain(dir),
+        dependencies: dependencies(dir)?,
+        ..host.clone()
+    })
+}
+
+/// Hex SHA-256 of `env`, so cells measured in the same environment can be matched.
+pub fn hash(env: &Environment) -> String {
+    let json = serde_json::to_vec(env).expect("an environment always serializes");
+    Sha256::digest(json)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Hex SHA-256 of the file at `path`.
+pub fn file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+    Ok(Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Commit checked out in the git repository containing `dir`, suffixed with `-dirty` when
+/// tracked files have uncommitted changes. `None` outside of git.
+pub fn harness_commit(dir: &Path) -> Option<String> {
+    let git = |args: &[&str]| {
+        let out = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let dirty = !git(&["status", "--porcelain", "--untracked-files=no"])?.is_empty();
+    Some(if dirty {
+        format!("{commit}-dirty")
+    } else {
+        commit
+    })
+}
+
+fn cpu_model() -> Option<String> {
+    if let Ok(info) = fs::read_to_string("/proc/cpuinfo") {
+        return info
+            .lines()
+            .find_map(|l| l.strip_prefix("model name")?.split_once(':'))
+            .map(|(_, model)| model.trim().to_string());
+    }
+    sysctl("machdep.cpu.brand_string")
+}
+
+fn memory_bytes() -> Option<u64> {
+    if let Ok(info) = fs::read_to_string("/proc/meminfo") {
+        let kb: u64 = info
+            .lines()
+            .find_map(|l| l.strip_prefix("MemTotal:"))?
+            .trim()
+            .strip_suffix("kB")?
+            .trim()
+            .parse()
+            .ok()?;
+        return Some(kb * 1024);
+    }
+    sysctl("hw.memsize")?.parse().ok()
+}
+
+fn sysctl(name: &str) -> Option<String> {
+    let out = Command::new("sysctl").args(["-n", name]).output().ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// `rustc -V` as cargo would run it from `dir`, honouring its `rust-toolchain.toml`.
+fn rustc(dir: &Path) -> Result<String> {
+    let out = Command::new("rustc")
+        .arg("-V")
+        .current_dir(dir)
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .output()
+        .context("cannot run rustc")?;
+    if !out.status.success() {
+        bail!("rustc -V failed in {}", dir.display());
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+fn toolchain(dir: &Path) -> Option<String> {
+    let out = Command::new("rustup")
+        .args(["show", "active-toolchain"])
+        .current_dir(dir)
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let name = stdout.split_whitespace().next()?;
+    out.status.success().then(|| name.to_string())
+}
+
+#[derive(Deserialize)]
+struct Lock {
+    #[serde(default)]
+    package: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+/// The tracked crates of the `Cargo.lock` next to the backend manifest in `dir`.
+fn dependencies(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let path = dir.join("Cargo.lock");
+    let lock =
+        fs::read_to_string(&path).with_context(|| format!("cannot read {}", path.display()))?;
+    let lock: Lock =
+        toml::from_str(&lock).with_context(|| format!("invalid {}", path.display()))?;
+    Ok(tracked(lock))
+}
+
+fn tracked(lock: Lock) -> BTreeMap<String, String> {
+    lock.package
+        .into_iter()
+        .filter_map(|p| {
+            let source = p.source?;
+            TRACKED.iter().any(|t| p.name.starts_with(t)).then(|| {
+                // git sources end in `#<full commit hash>`
+                let resolved = match source.split_once('#') {
+                    Some((_, rev)) => rev.to_string(),
+                    None => p.version,
+                };
+                (p.name, resolved)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_only_upstream_crates() {
+        let lock = r#"
+            [[package]]
+            name = "halo2"
+            version = "0.1.0"
+
+            [[package]]
+            name = "halo2-base"
+            version = "0.4.1"
+            source = "git+https://github.com/axiom-crypto/halo2-lib?branch=community-edition#0123abc"
+
+            [[package]]
+            name = "sp1-sdk"
+            version = "4.1.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "serde"
+            version = "1.0.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+        "#;
+        let tracked = tracked(toml::from_str(lock).unwrap());
+        assert_eq!(
+            tracked.into_iter().collect::<Vec<_>>(),
+            [
+                ("halo2-base".to_string(), "0123abc".to_string()),
+                ("sp1-sdk".to_string(), "4.1.0".to_string()),
+            ]
+        );
+    }
+}
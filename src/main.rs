@@ -0,0 +1,523 @@
+//! `yazkb` drives the task × backend benchmark matrix found under `tasks/`.
+
+mod cargo;
+mod cell;
+mod compare;
+mod config;
+mod discover;
+mod fingerprint;
+mod interrupt;
+mod report;
+mod store;
+mod verify;
+
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Result};
+use clap::{Args, Parser, Subcommand};
+use config::Config;
+use discover::Target;
+use harness::{artifacts::MANIFEST, record::Writer, Kind, Record, Status};
+
+/// Configuration read when `--config` is not given, if it exists.
+const DEFAULT_CONFIG: &str = "yazkb.toml";
+
+#[derive(Parser)]
+#[command(version, about = "Yet Another ZK Benchmark runner")]
+struct Cli {
+    /// Directory holding the `<task>/<backend>` benchmarks.
+    #[arg(long, global = true, default_value = "tasks")]
+    tasks_dir: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the discovered tasks and backends.
+    List,
+    /// Build and run the selected benchmarks.
+    Run(RunArgs),
+    /// Compare a results file against a baseline and report regressions.
+    Compare(CompareArgs),
+    /// Build the Markdown and HTML report with charts from results files.
+    Report(ReportArgs),
+    /// Verify saved proofs again, timing verification on its own.
+    Verify(VerifyArgs),
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Tasks to run, comma separated. Defaults to all.
+    #[arg(long, value_delimiter = ',')]
+    task: Vec<String>,
+
+    /// Backends to run, comma separated. Defaults to all.
+    #[arg(long, value_delimiter = ',')]
+    backend: Vec<String>,
+
+    /// Benchmark configuration. Defaults to `yazkb.toml` if it exists, built-in defaults
+    /// otherwise.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Input sizes, comma separated. Overrides the config.
+    #[arg(long, value_delimiter = ',', value_parser = harness::cli::at_least_one)]
+    sizes: Vec<usize>,
+
+    /// Untimed prove/verify rounds before sampling. Overrides the config.
+    #[arg(long)]
+    warmups: Option<usize>,
+
+    /// Timed prove/verify rounds per size. Overrides the config.
+    #[arg(long, value_parser = harness::cli::at_least_one)]
+    samples: Option<usize>,
+
+    /// Seed of the generated task inputs. Overrides the config.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// JSON Lines file the results are appended to. Defaults to
+    /// `results/<unix timestamp>.jsonl`.
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Directory the proof, verifying key, public output and manifest of every cell are
+    /// saved in, as `<task>/<backend>/<variant>/<size>/`. Defaults to the results file
+    /// without its extension.
+    #[arg(long)]
+    artifacts: Option<PathBuf>,
+
+    /// Wall-clock limit of each cell, e.g. `90s`, `30m` or `2h`. Overrides the config.
+    #[arg(long, value_parser = cell::parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Address space limit of each cell, e.g. `16G`. Overrides the config.
+    #[arg(long, value_parser = cell::parse_bytes)]
+    memory_limit: Option<u64>,
+
+    /// SQLite store of finished cells, kept across runs.
+    #[arg(long, default_value = "results/yazkb.sqlite")]
+    store: PathBuf,
+
+    /// Rerun cells the store already has a successful result for.
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct CompareArgs {
+    /// Results file to compare against.
+    baseline: PathBuf,
+
+    /// Results file to check.
+    new: PathBuf,
+
+    /// Slowdown of the median, in percent, above which a significant change fails.
+    #[arg(long, default_value_t = 5.0)]
+    threshold: f64,
+
+    /// Significance level of the Mann-Whitney U test.
+    #[arg(long, default_value_t = 0.05)]
+    alpha: f64,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    /// Results files, oldest first: later runs of a cell replace earlier ones.
+    #[arg(required = true)]
+    results: Vec<PathBuf>,
+
+    /// Directory the report and its charts are written to.
+    #[arg(long, default_value = "report")]
+    out: PathBuf,
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Artifact directories holding a `manifest.json`, or directories to search for them,
+    /// such as `results/<timestamp>`.
+    #[arg(required = true)]
+    artifacts: Vec<PathBuf>,
+
+    /// Benchmark configuration with the backend knobs the proofs were made with. Defaults to
+    /// `yazkb.toml` if it exists, built-in defaults otherwise.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Untimed verifications before sampling.
+    #[arg(long, default_value_t = 1)]
+    warmups: usize,
+
+    /// Timed verifications per proof.
+    #[arg(long, default_value_t = 3, value_parser = harness::cli::at_least_one)]
+    samples: usize,
+
+    /// JSON Lines file the timings are appended to. Defaults to
+    /// `results/verify-<unix timestamp>.jsonl`.
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Wall-clock limit of each verification, including setup and keygen.
+    #[arg(long, default_value = "1h", value_parser = cell::parse_duration)]
+    timeout: Duration,
+
+    /// Also regenerate the keys with the config's knobs and require their program ID to
+    /// match the saved one. Proofs are always verified against their saved verifying key.
+    #[arg(long)]
+    check_keys: bool,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::List => list(&cli.tasks_dir),
+        Command::Run(args) => run(&cli.tasks_dir, &args),
+        Command::Compare(args) => {
+            compare::compare(&args.baseline, &args.new, args.threshold, args.alpha)
+        }
+        Command::Report(args) => report::report(&args.results, &args.out).map(|(md, html)| {
+            println!(
+                "==> report written to {} and {}",
+                md.display(),
+                html.display()
+            );
+            true
+        }),
+        Command::Verify(args) => reverify(&cli.tasks_dir, &args),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn list(tasks_dir: &Path) -> Result<bool> {
+    for target in discover::discover(tasks_dir)? {
+        println!(
+            "{}/{}\t{}",
+            target.task,
+            target.backend,
+            target.manifest.display()
+        );
+    }
+    Ok(true)
+}
+
+/// Runs every selected target, each size in its own process. Returns `false` if any target
+/// failed to build or any cell did not end [`Status::Ok`]. On SIGINT or SIGTERM the running
+/// cell is cancelled and the remaining ones skipped, keeping the results of the finished ones.
+fn run(tasks_dir: &Path, args: &RunArgs) -> Result<bool> {
+    let targets = discover::discover(tasks_dir)?;
+    let config = load_config(args.config.as_deref(), &targets)?;
+    let targets = select(targets, args)?;
+    let output = output_path(args.output.as_deref(), "")?;
+    let artifacts = match &args.artifacts {
+        Some(dir) => std::path::absolute(dir)?,
+        None => output.with_extension(""),
+    };
+
+    let host = fingerprint::host();
+    if let Some(dir) = args.store.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let store = store::Store::open(
+        &args.store,
+        fingerprint::harness_commit(tasks_dir).as_deref(),
+        &output,
+    )?;
+
+    interrupt::trap();
+    let mut failed = vec![];
+    // every target is built, and checks its knobs against its sizes, before the first cell
+    // runs, so a bad config does not fail a sweep midway
+    let mut built = vec![];
+    for target in &targets {
+        if interrupt::requested() {
+            break;
+        }
+        let name = format!("{}/{}", target.task, target.backend);
+        let settings = settings(&config, target, args)?;
+        let params = settings.params.to_string();
+        println!("==> {name}: building");
+        let result = cargo::build(target).and_then(|exe| {
+            let info = cell::describe(&exe, target.crate_dir(), &params, &settings.sizes)?;
+            let env = fingerprint::backend(&host, target.crate_dir())?;
+            Ok((exe, info, env))
+        });
+        match result {
+            Ok((exe, info, env)) => built.push((target, name, settings, exe, info, env)),
+            // cargo got the Ctrl-C too
+            Err(_) if interrupt::requested() => break,
+            Err(e) => {
+                eprintln!("==> {name}: build failed: {e:#}");
+                failed.push(name);
+            }
+        }
+    }
+
+    'targets: for (target, name, settings, exe, info, env) in built {
+        if interrupt::requested() {
+            break;
+        }
+        let params = settings.params.to_string();
+        let env_hash = fingerprint::hash(&env);
+        let exe_hash = fingerprint::file(&exe)?;
+        let mut config = settings.opts.to_args();
+        config.extend(["--params".to_string(), params.clone()]);
+        let config = config.join(" ");
+        for &size in &settings.sizes {
+            if interrupt::requested() {
+                break 'targets;
+            }
+            let key = store::CellKey {
+                task: &info.task,
+                backend: &info.backend,
+                variant: &info.variant,
+                size,
+                config: &config,
+                fingerprint: &env_hash,
+                executable: &exe_hash,
+            };
+            if !args.force {
+                if let Some((run, records)) = store.completed(&key)? {
+                    let saved = records.iter().find_map(|r| match &r.kind {
+                        Kind::Outcome {
+                            artifacts: Some(dir),
+                            ..
+                        } => Some(dir),
+                        _ => None,
+                    });
+                    match saved {
+                        Some(dir) => println!(
+                            "==> {name}({size}): done in run {run}, skipping, proofs in {}",
+                            dir.display()
+                        ),
+                        None => println!("==> {name}({size}): done in run {run}, skipping"),
+                    }
+                    let mut out = Writer::open(Some(&output))?;
+                    for record in &records {
+                        out.write(record)?;
+                    }
+                    continue;
+                }
+            }
+
+            println!("==> {name}({size}): running");
+            let start = fs::metadata(&output).map_or(0, |m| m.len());
+            let mut cell_args = vec!["--sizes".to_string(), size.to_string()];
+            cell_args.extend(settings.opts.to_args());
+            cell_args.extend(["--params".to_string(), params.clone()]);
+            cell_args.extend(["--output".to_string(), output.display().to_string()]);
+            let dir = artifacts
+                .join(&info.task)
+                .join(&info.backend)
+                .join(&info.variant);
+            cell_args.extend(["--artifacts".to_string(), dir.display().to_string()]);
+            let outcome = cell::run(&exe, &cell_args, target.crate_dir(), settings.limits)?;
+
+            // the backend saves each size into its own subdirectory
+            let saved = dir.join(size.to_string());
+            if outcome.status == Status::Cancelled {
+                if let Err(e) = fs::remove_dir_all(&saved) {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        eprintln!("==> cannot remove {}: {e}", saved.display());
+                    }
+                }
+            }
+            let kind = Kind::Outcome {
+                status: outcome.status,
+                message: outcome.message.clone(),
+                environment: Box::new(env.clone()),
+                artifacts: saved.join(MANIFEST).is_file().then_some(saved),
+            };
+            let record = Record {
+                peak_rss_bytes: outcome.peak_rss,
+                seed: Some(settings.opts.seed),
+                ..info.record(size, None, kind)
+            };
+            Writer::open(Some(&output))?.write(&record)?;
+            store.insert(&key, outcome.status, &records_since(&output, start)?)?;
+            println!("==> {name}({size}): {:?}", outcome.status);
+            if outcome.status != Status::Ok {
+                failed.push(format!("{name}({size})"));
+            }
+        }
+    }
+
+    if output.exists() {
+        print_summary(&harness::record::read(&output)?);
+        println!("==> results written to {}", output.display());
+    }
+    if artifacts.exists() {
+        println!("==> proofs and keys saved in {}", artifacts.display());
+    }
+    for name in &failed {
+        println!("==> failed: {name}");
+    }
+    if interrupt::requested() {
+        println!("==> interrupted, the remaining cells were not run");
+        return Ok(false);
+    }
+    Ok(failed.is_empty())
+}
+
+/// Verifies the saved proofs of `args` with their backends and prints the timings.
+fn reverify(tasks_dir: &Path, args: &VerifyArgs) -> Result<bool> {
+    let targets = discover::discover(tasks_dir)?;
+    let config = load_config(args.config.as_deref(), &targets)?;
+    let output = output_path(args.output.as_deref(), "verify-")?;
+    let opts = harness::Options {
+        warmups: args.warmups,
+        samples: args.samples,
+        ..Default::default()
+    };
+    let ok = verify::verify(
+        &targets,
+        &config,
+        &args.artifacts,
+        &opts,
+        args.timeout,
+        args.check_keys,
+        &output,
+    )?;
+    if output.exists() {
+        print_summary(&harness::record::read(&output)?);
+        println!("==> results written to {}", output.display());
+    }
+    Ok(ok)
+}
+
+/// `path`, or `yazkb.toml` if it exists, or the built-in defaults.
+fn load_config(path: Option<&Path>, targets: &[Target]) -> Result<Config> {
+    match path {
+        Some(path) => Config::load(path, targets),
+        None if Path::new(DEFAULT_CONFIG).exists() => {
+            Config::load(Path::new(DEFAULT_CONFIG), targets)
+        }
+        None => Ok(Config::default()),
+    }
+}
+
+fn print_summary(records: &[Record]) {
+    for r in records {
+        let name = format!("{}/{}/{}", r.task, r.backend, r.variant);
+        match &r.kind {
+            Kind::Summary(s) => {
+                let peak = r.peak_rss_bytes.map_or("-".into(), |b| {
+                    format!("{:.1} MiB", b as f64 / (1 << 20) as f64)
+                });
+                println!(
+                    "{name}\t{}\t{:?}\tmedian {:.3} ms\tstddev {:.3} ms\toutliers {}\tpeak rss {}\tproof {}",
+                    r.size,
+                    r.phase.unwrap(),
+                    s.median / 1e6,
+                    s.stddev / 1e6,
+                    s.outliers,
+                    peak,
+                    r.sizes
+                        .proof_bytes
+                        .map_or("-".into(), |b| format!("{b} B")),
+                );
+            }
+            Kind::Cold { nanos } => {
+                let peak = r.peak_rss_bytes.map_or("-".into(), |b| {
+                    format!("{:.1} MiB", b as f64 / (1 << 20) as f64)
+                });
+                println!(
+                    "{name}\t{}\t{:?}\tcold {:.3} ms\tpeak rss {}",
+                    r.size,
+                    r.phase.unwrap(),
+                    *nanos as f64 / 1e6,
+                    peak,
+                );
+            }
+            Kind::Outcome {
+                status, message, ..
+            } if *status != Status::Ok => {
+                let message = message.as_deref().unwrap_or("");
+                println!("{name}\t{}\t{status:?}\t{message}", r.size);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The settings of `target` from the config, overridden by the command line.
+fn settings(config: &Config, target: &Target, args: &RunArgs) -> Result<config::Resolved> {
+    let mut settings = config.resolve(target)?;
+    if !args.sizes.is_empty() {
+        settings.sizes = args.sizes.clone();
+    }
+    if let Some(warmups) = args.warmups {
+        settings.opts.warmups = warmups;
+    }
+    if let Some(samples) = args.samples {
+        settings.opts.samples = samples;
+    }
+    if let Some(seed) = args.seed {
+        settings.opts.seed = seed;
+    }
+    if let Some(timeout) = args.timeout {
+        settings.limits.timeout = timeout;
+    }
+    if args.memory_limit.is_some() {
+        settings.limits.memory = args.memory_limit;
+    }
+    Ok(settings)
+}
+
+/// Records appended to the results file at `path` after byte `offset`.
+fn records_since(path: &Path, offset: u64) -> Result<Vec<Record>> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut lines = String::new();
+    file.read_to_string(&mut lines)?;
+    harness::record::parse(&lines, &path.display().to_string())
+}
+
+/// Absolute path of the results file, so the backends can write to it from their own
+/// directories.
+fn output_path(output: Option<&Path>, prefix: &str) -> Result<PathBuf> {
+    let path = match output {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            PathBuf::from("results").join(format!("{prefix}{now}.jsonl"))
+        }
+    };
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(std::path::absolute(path)?)
+}
+
+/// Keeps the targets matching `--task` and `--backend`, rejecting names that match nothing.
+fn select(targets: Vec<Target>, args: &RunArgs) -> Result<Vec<Target>> {
+    for task in &args.task {
+        if !targets.iter().any(|t| &t.task == task) {
+            bail!("unknown task `{task}`, see `yazkb list`");
+        }
+    }
+    for backend in &args.backend {
+        if !targets.iter().any(|t| &t.backend == backend) {
+            bail!("unknown backend `{backend}`, see `yazkb list`");
+        }
+    }
+    Ok(targets
+        .into_iter()
+        .filter(|t| args.task.is_empty() || args.task.contains(&t.task))
+        .filter(|t| args.backend.is_empty() || args.backend.contains(&t.backend))
+        .collect())
+}
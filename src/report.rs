@@ -0,0 +1,467 @@
+//! Builds the Markdown and HTML benchmark report from results files.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use harness::{Kind, Phase, Record, Status};
+
+/// Phases in table column order.
+const PHASES: [Phase; 5] = [
+    Phase::Setup,
+    Phase::Keygen,
+    Phase::Execute,
+    Phase::Prove,
+    Phase::Verify,
+];
+
+/// Backend whose execution time is the baseline of the overhead ratios.
+const NATIVE: &str = "native";
+
+/// Phases that get a chart.
+const CHARTED: [Phase; 2] = [Phase::Prove, Phase::Verify];
+
+/// Series colors, cycled through in backend order.
+const COLORS: [&str; 6] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b",
+];
+
+/// Everything known about one (backend, variant, size) cell of a task.
+#[derive(Default)]
+struct Cell {
+    /// Median nanoseconds per phase.
+    medians: BTreeMap<Phase, f64>,
+    /// Nanoseconds of the first prove in a fresh process.
+    cold_prove: Option<u64>,
+    proof_bytes: Option<usize>,
+    prove_rss: Option<u64>,
+    status: Option<Status>,
+}
+
+/// task -> "backend/variant" -> size -> cell
+type Cells = BTreeMap<String, BTreeMap<String, BTreeMap<usize, Cell>>>;
+
+/// Writes `report.md`, `report.html` and one SVG chart per task and charted phase into `out`.
+/// Returns the paths of the two reports.
+pub fn report(results: &[PathBuf], out: &Path) -> Result<(PathBuf, PathBuf)> {
+    let mut records = vec![];
+    for path in results {
+        records.extend(harness::record::read(path)?);
+    }
+    let cells = cells(&records);
+    if cells.is_empty() {
+        bail!("no results to report");
+    }
+    fs::create_dir_all(out).with_context(|| format!("cannot create {}", out.display()))?;
+
+    let mut md = String::from("# Benchmark results\n");
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Benchmark results</title>\n<style>\n\
+         body { font-family: sans-serif; max-width: 960px; margin: auto; }\n\
+         table { border-collapse: collapse; }\n\
+         th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: right; }\n\
+         </style>\n</head>\n<body>\n<h1>Benchmark results</h1>\n",
+    );
+    for (task, backends) in &cells {
+        writeln!(md, "\n## {task}\n")?;
+        writeln!(html, "<h2>{task}</h2>")?;
+
+        let (header, rows) = table(backends);
+        writeln!(md, "| {} |", header.join(" | "))?;
+        writeln!(md, "|{}", " --- |".repeat(header.len()))?;
+        writeln!(html, "<table>\n<tr>{}</tr>", wrap("th", &header))?;
+        for row in &rows {
+            writeln!(md, "| {} |", row.join(" | "))?;
+            writeln!(html, "<tr>{}</tr>", wrap("td", row))?;
+        }
+        writeln!(html, "</table>")?;
+
+        for phase in CHARTED {
+            let Some(svg) = chart(task, phase, backends) else {
+                continue;
+            };
+            let file = format!("{task}-{}.svg", phase_name(phase));
+            fs::write(out.join(&file), &svg)?;
+            writeln!(md, "\n![{task} {}]({file})", phase_name(phase))?;
+            writeln!(html, "{svg}")?;
+        }
+    }
+    writeln!(html, "</body>\n</html>")?;
+
+    let (md_path, html_path) = (out.join("report.md"), out.join("report.html"));
+    fs::write(&md_path, md)?;
+    fs::write(&html_path, html)?;
+    Ok((md_path, html_path))
+}
+
+/// Groups the summaries and outcomes by cell, keeping the latest run of each, so passing the
+/// results files oldest first reports the latest run. A run ends with the runner's outcome
+/// record, and starts anew with any record after it or with the first setup sample.
+fn cells(records: &[Record]) -> Cells {
+    let mut cells = Cells::new();
+    for r in records {
+        let cell = cells
+            .entry(r.task.clone())
+            .or_default()
+            .entry(format!("{}/{}", r.backend, r.variant))
+            .or_default()
+            .entry(r.size)
+            .or_default();
+        let starts_run = matches!(
+            (&r.kind, r.phase),
+            (Kind::Sample { sample: 0, .. }, Some(Phase::Setup))
+        );
+        if cell.status.is_some() || starts_run {
+            // a newer run, whose phases must not mix with the medians of an older one
+            *cell = Cell::default();
+        }
+        match (&r.kind, r.phase) {
+            (Kind::Summary(s), Some(phase)) => {
+                cell.medians.insert(phase, s.median);
+                if phase == Phase::Prove {
+                    cell.proof_bytes = r.sizes.proof_bytes;
+                    cell.prove_rss = r.peak_rss_bytes;
+                }
+            }
+            (Kind::Cold { nanos }, Some(Phase::Prove)) => cell.cold_prove = Some(*nanos),
+            (Kind::Outcome { status, .. }, _) => cell.status = Some(*status),
+            _ => {}
+        }
+    }
+    cells
+}
+
+fn table(backends: &BTreeMap<String, BTreeMap<usize, Cell>>) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut header = vec!["backend".to_string(), "size".to_string()];
+    header.extend(PHASES.iter().map(|&p| format!("{} (ms)", phase_name(p))));
+    header.push("cold prove (ms)".to_string());
+    header.extend(["proof (B)".to_string(), "prove peak RSS (MiB)".to_string()]);
+    header.extend(["execute / native".to_string(), "prove / native".to_string()]);
+
+    // median native run time by size
+    let native: BTreeMap<usize, f64> = backends
+        .iter()
+        .filter(|(backend, _)| backend.split('/').next() == Some(NATIVE))
+        .flat_map(|(_, sizes)| sizes)
+        .filter_map(|(&size, cell)| Some((size, *cell.medians.get(&Phase::Execute)?)))
+        .collect();
+
+    let mut rows = vec![];
+    for (backend, sizes) in backends {
+        for (size, cell) in sizes {
+            let mut row = vec![backend.clone(), size.to_string()];
+            match cell.status {
+                Some(status) if status != Status::Ok => {
+                    // a failed cell may still have summaries of the phases it finished
+                    row.extend(PHASES.iter().map(|p| match cell.medians.get(p) {
+                        Some(nanos) => format!("{:.3}", nanos / 1e6),
+                        None => format!("{status:?}").to_lowercase(),
+                    }));
+                }
+                _ => row.extend(PHASES.iter().map(|p| {
+                    cell.medians
+                        .get(p)
+                        .map_or("-".into(), |nanos| format!("{:.3}", nanos / 1e6))
+                })),
+            }
+            row.push(
+                cell.cold_prove
+                    .map_or("-".into(), |nanos| format!("{:.3}", nanos as f64 / 1e6)),
+            );
+            row.push(cell.proof_bytes.map_or("-".into(), |b| b.to_string()));
+            row.push(cell.prove_rss.map_or("-".into(), |b| {
+                format!("{:.1}", b as f64 / (1 << 20) as f64)
+            }));
+            for phase in [Phase::Execute, Phase::Prove] {
+                let ratio = match (cell.medians.get(&phase), native.get(size)) {
+                    (Some(t), Some(&base)) if base > 0.0 => match t / base {
+                        r if r < 10.0 => format!("{r:.1}x"),
+                        r => format!("{r:.0}x"),
+                    },
+                    _ => "-".into(),
+                };
+                row.push(ratio);
+            }
+            rows.push(row);
+        }
+    }
+    (header, rows)
+}
+
+fn wrap(tag: &str, cells: &[String]) -> String {
+    cells
+        .iter()
+        .map(|c| format!("<{tag}>{c}</{tag}>"))
+        .collect()
+}
+
+fn phase_name(phase: Phase) -> String {
+    format!("{phase:?}").to_lowercase()
+}
+
+/// Log-log chart of the median time of `phase` against the input size, one line per backend.
+/// `None` if no backend has a measurement of the phase.
+fn chart(
+    task: &str,
+    phase: Phase,
+    backends: &BTreeMap<String, BTreeMap<usize, Cell>>,
+) -> Option<String> {
+    const W: f64 = 640.0;
+    const H: f64 = 400.0;
+    // left, right, top, bottom margins
+    const L: f64 = 70.0;
+    const R: f64 = 160.0;
+    const T: f64 = 30.0;
+    const B: f64 = 50.0;
+
+    // (backend, [(size, ms)]), only positive values fit on a log scale
+    let series: Vec<(&String, Vec<(f64, f64)>)> = backends
+        .iter()
+        .map(|(backend, sizes)| {
+            let points = sizes
+                .iter()
+                .filter_map(|(&size, cell)| Some((size as f64, cell.medians.get(&phase)? / 1e6)))
+                .filter(|&(x, y)| x > 0.0 && y > 0.0)
+                .collect();
+            (backend, points)
+        })
+        .filter(|(_, points): &(_, Vec<_>)| !points.is_empty())
+        .collect();
+    let points = series.iter().flat_map(|(_, p)| p);
+    let (xs, ys): (Vec<f64>, Vec<f64>) = points.copied().unzip();
+    if xs.is_empty() {
+        return None;
+    }
+    // whole decades, at least one wide
+    let decades = |v: &[f64]| {
+        let lo = v
+            .iter()
+            .copied()
+            .fold(f64::INFINITY, f64::min)
+            .log10()
+            .floor();
+        let hi = v.iter().copied().fold(0.0, f64::max).log10().ceil();
+        (lo, hi.max(lo + 1.0))
+    };
+    let ((x0, x1), (y0, y1)) = (decades(&xs), decades(&ys));
+    let px = |x: f64| L + (x.log10() - x0) / (x1 - x0) * (W - L - R);
+    let py = |y: f64| H - B - (y.log10() - y0) / (y1 - y0) * (H - T - B);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{W}\" height=\"{H}\" \
+         viewBox=\"0 0 {W} {H}\" font-family=\"sans-serif\" font-size=\"12\">"
+    );
+    let _ = writeln!(
+        svg,
+        "<text x=\"{}\" y=\"18\" text-anchor=\"middle\" font-size=\"14\">{task}: {} time</text>",
+        (L + W - R) / 2.0,
+        phase_name(phase)
+    );
+    for e in x0 as i32..=x1 as i32 {
+        let x = px(10f64.powi(e));
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{x:.1}\" y1=\"{T}\" x2=\"{x:.1}\" y2=\"{}\" stroke=\"#ddd\"/>\
+             <text x=\"{x:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            H - B,
+            H - B + 16.0,
+            power_of_ten(e)
+        );
+    }
+    for e in y0 as i32..=y1 as i32 {
+        let y = py(10f64.powi(e));
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{L}\" y1=\"{y:.1}\" x2=\"{}\" y2=\"{y:.1}\" stroke=\"#ddd\"/>\
+             <text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            W - R,
+            L - 6.0,
+            y + 4.0,
+            power_of_ten(e)
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">input size</text>\
+         <text transform=\"translate(16 {}) rotate(-90)\" text-anchor=\"middle\">ms</text>",
+        (L + W - R) / 2.0,
+        H - 12.0,
+        (T + H - B) / 2.0
+    );
+
+    for (i, (backend, points)) in series.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let path: Vec<String> = points
+            .iter()
+            .map(|&(x, y)| format!("{:.1},{:.1}", px(x), py(y)))
+            .collect();
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"/>",
+            path.join(" ")
+        );
+        for &(x, y) in points {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{color}\"/>",
+                px(x),
+                py(y)
+            );
+        }
+        let ly = T + 10.0 + 18.0 * i as f64;
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{}\" y1=\"{ly}\" x2=\"{}\" y2=\"{ly}\" stroke=\"{color}\" stroke-width=\"2\"/>\
+             <text x=\"{}\" y=\"{}\">{backend}</text>",
+            W - R + 10.0,
+            W - R + 30.0,
+            W - R + 36.0,
+            ly + 4.0
+        );
+    }
+    svg.push_str("</svg>\n");
+    Some(svg)
+}
+
+/// Axis label of `10^e`, e.g. `0.01`, `1`, `10k` or `1M`.
+fn power_of_ten(e: i32) -> String {
+    match e {
+        ..=-1 => format!("{:.*}", -e as usize, 10f64.powi(e)),
+        0..=2 => format!("{}", 10u64.pow(e as u32)),
+        3..=5 => format!("{}k", 10u64.pow(e as u32 - 3)),
+        _ => format!("{}M", 10f64.powi(e - 6)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use harness::{stats::Summary, BackendInfo, Environment};
+
+    use super::*;
+
+    fn info(backend: &str) -> BackendInfo {
+        BackendInfo {
+            task: "fibo".into(),
+            backend: backend.into(),
+            variant: "v".into(),
+            versions: BTreeMap::new(),
+        }
+    }
+
+    /// The records of a run of `backend` on `size` with the given phase medians in ms.
+    fn run(backend: &str, size: usize, phases: &[(Phase, u64)], status: Status) -> Vec<Record> {
+        let info = info(backend);
+        let mut records = vec![];
+        for &(phase, ms) in phases {
+            let nanos = ms * 1_000_000;
+            let sample = Kind::Sample {
+                sample: 0,
+                nanos,
+                outlier: false,
+            };
+            records.push(info.record(size, Some(phase), sample));
+            let summary = Kind::Summary(Summary::new(&[nanos]));
+            records.push(info.record(size, Some(phase), summary));
+        }
+        let outcome = Kind::Outcome {
+            status,
+            message: None,
+            environment: Box::new(Environment::default()),
+            artifacts: None,
+        };
+        records.push(info.record(size, None, outcome));
+        records
+    }
+
+    #[test]
+    fn a_failed_rerun_replaces_the_earlier_times() {
+        let mut records = run(
+            "halo2",
+            10,
+            &[(Phase::Setup, 1), (Phase::Prove, 50)],
+            Status::Ok,
+        );
+        records.extend(run("halo2", 10, &[(Phase::Setup, 2)], Status::Oom));
+        // a run killed before writing anything
+        records.extend(run("halo2", 100, &[(Phase::Prove, 70)], Status::Ok));
+        records.extend(run("halo2", 100, &[], Status::Dnf));
+        let cells = cells(&records);
+        let sizes = &cells["fibo"]["halo2/v"];
+        assert_eq!(sizes[&10].status, Some(Status::Oom));
+        assert_eq!(sizes[&10].medians, BTreeMap::from([(Phase::Setup, 2e6)]));
+        assert_eq!(sizes[&100].status, Some(Status::Dnf));
+        assert!(sizes[&100].medians.is_empty());
+
+        let (_, rows) = table(&cells["fibo"]);
+        assert_eq!(
+            rows[0][..7],
+            ["halo2/v", "10", "2.000", "oom", "oom", "oom", "oom"]
+        );
+        assert_eq!(rows[1][2..7], ["dnf", "dnf", "dnf", "dnf", "dnf"]);
+    }
+
+    #[test]
+    fn tables_times_and_native_ratios() {
+        let mut records = run("native", 10, &[(Phase::Execute, 2)], Status::Ok);
+        records.extend(run(
+            "sp1",
+            10,
+            &[(Phase::Execute, 4), (Phase::Prove, 300)],
+            Status::Ok,
+        ));
+        let cells = cells(&records);
+        let (header, rows) = table(&cells["fibo"]);
+        assert_eq!(header.len(), rows[0].len());
+        assert_eq!(rows[0][..4], ["native/v", "10", "-", "-"]);
+        assert_eq!(rows[0][4], "2.000");
+        assert_eq!(rows[1][5], "300.000");
+        // execute and prove over the native execution
+        assert_eq!(rows[1][rows[1].len() - 2..], ["2.0x", "150x"]);
+    }
+
+    #[test]
+    fn charts_one_line_per_backend() {
+        let mut records = run("risc0", 10, &[(Phase::Prove, 10)], Status::Ok);
+        records.extend(run("risc0", 1000, &[(Phase::Prove, 200)], Status::Ok));
+        records.extend(run("sp1", 10, &[(Phase::Prove, 30)], Status::Ok));
+        let cells = cells(&records);
+        let svg = chart("fibo", Phase::Prove, &cells["fibo"]).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert!(svg.contains(">risc0/v</text>") && svg.contains(">sp1/v</text>"));
+        assert!(chart("fibo", Phase::Verify, &cells["fibo"]).is_none());
+    }
+
+    #[test]
+    fn writes_the_reports_and_charts() {
+        let dir = std::env::temp_dir().join(format!("yazkb-report-{}", std::process::id()));
+        let results = dir.join("results.jsonl");
+        fs::create_dir_all(&dir).unwrap();
+        let lines: Vec<String> = run("sp1", 10, &[(Phase::Prove, 30)], Status::Ok)
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap() + "\n")
+            .collect();
+        fs::write(&results, lines.concat()).unwrap();
+        let (md, html) = report(&[results], &dir).unwrap();
+        let (md, html) = (
+            fs::read_to_string(md).unwrap(),
+            fs::read_to_string(html).unwrap(),
+        );
+        let chart = dir.join("fibo-prove.svg").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(md.contains("## fibo") && md.contains("| sp1/v | 10 |"));
+        assert!(md.contains("![fibo prove](fibo-prove.svg)"));
+        assert!(html.contains("<td>sp1/v</td>") && html.contains("<svg"));
+        assert!(chart);
+    }
+}
@@ -0,0 +1,222 @@
+//! SQLite store of every finished cell, so interrupted sweeps can resume and history is kept
+//! across runs.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use harness::{Record, Status};
+use rusqlite::{params, Connection, OptionalExtension};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at INTEGER NOT NULL,
+    -- git commit of the harness, with a `-dirty` suffix for uncommitted changes
+    harness_commit TEXT,
+    output TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS cells (
+    id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    task TEXT NOT NULL,
+    backend TEXT NOT NULL,
+    variant TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    config TEXT NOT NULL,
+    fingerprint TEXT NOT NULL,
+    -- hex SHA-256 of the task binary
+    executable TEXT NOT NULL,
+    status TEXT NOT NULL,
+    -- every record of the cell, as JSON Lines
+    records TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS cells_key
+ON cells (task, backend, variant, size, config, fingerprint, executable);
+";
+
+/// What makes two runs of a cell interchangeable.
+pub struct CellKey<'a> {
+    pub task: &'a str,
+    pub backend: &'a str,
+    pub variant: &'a str,
+    pub size: usize,
+    /// The sampling options the cell ran with.
+    pub config: &'a str,
+    /// See [`crate::fingerprint::hash`].
+    pub fingerprint: &'a str,
+    /// Hex SHA-256 of the task binary. It embeds the guest program or circuit, so unlike
+    /// the `program_id` of the records, which is only known after keygen, it can be checked
+    /// before running the cell.
+    pub executable: &'a str,
+}
+
+pub struct Store {
+    conn: Connection,
+    run: i64,
+}
+
+impl Store {
+    /// Opens or creates the store at `path` and records a new run writing to `output`.
+    pub fn open(path: &Path, harness_commit: Option<&str>, output: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("cannot open store {}", path.display()))?;
+        conn.execute_batch(SCHEMA)?;
+        conn.execute(
+            "INSERT INTO runs (started_at, harness_commit, output) \
+             VALUES (strftime('%s', 'now'), ?1, ?2)",
+            params![harness_commit, output.display().to_string()],
+        )?;
+        let run = conn.last_insert_rowid();
+        Ok(Self { conn, run })
+    }
+
+    /// The records of the latest run of `key` that ended [`Status::Ok`], with the ID of the
+    /// run it belongs to.
+    pub fn completed(&self, key: &CellKey) -> Result<Option<(i64, Vec<Record>)>> {
+        let found: Option<(i64, String)> = self
+            .conn
+            .query_row(
+                "SELECT run_id, records FROM cells \
+                 WHERE task = ?1 AND backend = ?2 AND variant = ?3 AND size = ?4 \
+                 AND config = ?5 AND fingerprint = ?6 AND executable = ?7 AND status = ?8 \
+                 ORDER BY id DESC LIMIT 1",
+                params![
+                    key.task,
+                    key.backend,
+                    key.variant,
+                    key.size as i64,
+                    key.config,
+                    key.fingerprint,
+                    key.executable,
+                    status_name(Status::Ok)
+                ],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((run, lines)) = found else {
+            return Ok(None);
+        };
+        let records = lines
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .context("invalid records in store")?;
+        Ok(Some((run, records)))
+    }
+
+    /// Saves a finished cell of the current run, whatever its status.
+    pub fn insert(&self, key: &CellKey, status: Status, records: &[Record]) -> Result<()> {
+        let mut lines = String::new();
+        for record in records {
+            lines.push_str(&serde_json::to_string(record)?);
+            lines.push('\n');
+        }
+        self.conn.execute(
+            "INSERT INTO cells \
+             (run_id, task, backend, variant, size, config, fingerprint, executable, status, \
+             records) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                self.run,
+                key.task,
+                key.backend,
+                key.variant,
+                key.size as i64,
+                key.config,
+                key.fingerprint,
+                key.executable,
+                status_name(status),
+                lines
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn status_name(status: Status) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, path::PathBuf};
+
+    use harness::{BackendInfo, Kind};
+
+    use super::*;
+
+    const KEY: CellKey = CellKey {
+        task: "fibo",
+        backend: "native",
+        variant: "rust",
+        size: 10,
+        config: "--warmups 1 --samples 3",
+        fingerprint: "env",
+        executable: "exe",
+    };
+
+    fn temp_store(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("yazkb-{name}-{}.sqlite", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn records(nanos: u64) -> Vec<Record> {
+        let info = BackendInfo {
+            task: "fibo".into(),
+            backend: "native".into(),
+            variant: "rust".into(),
+            versions: BTreeMap::new(),
+        };
+        vec![info.record(10, None, Kind::Cold { nanos })]
+    }
+
+    #[test]
+    fn finds_the_latest_ok_cell() {
+        let path = temp_store("store");
+        let first = Store::open(&path, None, Path::new("a.jsonl")).unwrap();
+        assert_eq!(first.completed(&KEY).unwrap(), None);
+        first.insert(&KEY, Status::Ok, &records(1)).unwrap();
+        let second = Store::open(&path, Some("abc"), Path::new("b.jsonl")).unwrap();
+        second.insert(&KEY, Status::Ok, &records(2)).unwrap();
+        // a failed rerun does not hide the last success
+        second.insert(&KEY, Status::Panic, &records(3)).unwrap();
+        let found = second.completed(&KEY).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(found, Some((second.run, records(2))));
+        assert_ne!(first.run, second.run);
+    }
+
+    #[test]
+    fn skips_only_matching_ok_cells() {
+        let path = temp_store("skip");
+        let store = Store::open(&path, None, Path::new("a.jsonl")).unwrap();
+        store.insert(&KEY, Status::Ok, &records(1)).unwrap();
+        let failed = CellKey { size: 20, ..KEY };
+        store.insert(&failed, Status::Oom, &records(2)).unwrap();
+        let others = [
+            CellKey { size: 11, ..KEY },
+            CellKey {
+                config: "--warmups 0 --samples 3",
+                ..KEY
+            },
+            CellKey {
+                fingerprint: "other env",
+                ..KEY
+            },
+            CellKey {
+                executable: "rebuilt",
+                ..KEY
+            },
+            failed,
+        ];
+        let found: Vec<_> = others
+            .iter()
+            .map(|key| store.completed(key).unwrap())
+            .collect();
+        fs::remove_file(path).unwrap();
+        assert!(found.iter().all(Option::is_none));
+    }
+}
@@ -0,0 +1,119 @@
+//! Verifies saved proofs again without proving, e.g. after a verifier upgrade.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use harness::{
+    artifacts::{Manifest, MANIFEST},
+    Status,
+};
+
+use crate::{cargo, cell, config::Config, discover::Target};
+
+/// Runs the verifier of the matching backend on every artifact directory at or below
+/// `paths`, appending the timings to `output`. Returns `false` if any proof failed.
+pub fn verify(
+    targets: &[Target],
+    config: &Config,
+    paths: &[PathBuf],
+    opts: &harness::Options,
+    timeout: Duration,
+    check_keys: bool,
+    output: &Path,
+) -> Result<bool> {
+    let mut found = vec![];
+    for path in paths {
+        artifact_dirs(path, &mut found)?;
+    }
+    if found.is_empty() {
+        bail!("no {MANIFEST} found");
+    }
+    // grouped by backend, so each one is built once
+    let mut by_target: BTreeMap<(String, String), Vec<PathBuf>> = BTreeMap::new();
+    for dir in found {
+        let manifest = Manifest::read(&dir)?;
+        by_target
+            .entry((manifest.task, manifest.backend))
+            .or_default()
+            .push(std::path::absolute(dir)?);
+    }
+
+    let mut ok = true;
+    for ((task, backend), dirs) in by_target {
+        let name = format!("{task}/{backend}");
+        let Some(target) = targets
+            .iter()
+            .find(|t| t.task == task && t.backend == backend)
+        else {
+            eprintln!("==> {name}: unknown backend, see `yazkb list`");
+            ok = false;
+            continue;
+        };
+        // setup, and keygen with `check_keys`, follow the knobs, so they must be the ones the
+        // proofs were made with
+        let params = config.resolve(target)?.params.to_string();
+        println!("==> {name}: building");
+        let exe = match cargo::build(target) {
+            Ok(exe) => exe,
+            Err(e) => {
+                eprintln!("==> {name}: build failed: {e:#}");
+                ok = false;
+                continue;
+            }
+        };
+        for dir in dirs {
+            println!("==> {name}: verifying {}", dir.display());
+            let start = fs::metadata(output).map_or(0, |m| m.len());
+            let mut args = vec!["--verify".to_string(), dir.display().to_string()];
+            args.extend([
+                "--warmups".to_string(),
+                opts.warmups.to_string(),
+                "--samples".to_string(),
+                opts.samples.to_string(),
+                "--params".to_string(),
+                params.clone(),
+                "--output".to_string(),
+                output.display().to_string(),
+            ]);
+            if check_keys {
+                args.push("--check-keys".to_string());
+            }
+            let limits = cell::Limits {
+                timeout,
+                memory: None,
+            };
+            let outcome = cell::run(&exe, &args, target.crate_dir(), limits)?;
+            let written = fs::metadata(output).map_or(0, |m| m.len()) > start;
+            match outcome.status {
+                Status::Ok if !written => println!("==> {name}: no proof to verify"),
+                Status::Ok => println!("==> {name}: verified"),
+                status => {
+                    let message = outcome.message.unwrap_or_default();
+                    println!("==> {name}: {status:?} {message}");
+                    ok = false;
+                }
+            }
+        }
+    }
+    Ok(ok)
+}
+
+/// Collects `path` if it holds a manifest, its subdirectories that do otherwise.
+fn artifact_dirs(path: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    if path.join(MANIFEST).is_file() {
+        found.push(path.to_path_buf());
+        return Ok(());
+    }
+    let entries = fs::read_dir(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let mut dirs: Vec<PathBuf> = entries.map(|e| Ok(e?.path())).collect::<Result<_>>()?;
+    dirs.sort();
+    for dir in dirs.iter().filter(|d| d.is_dir()) {
+        artifact_dirs(dir, found)?;
+    }
+    Ok(())
+}
@@ -2,11 +2,11 @@ use halo2_base::{
     gates::{
         circuit::{builder::BaseCircuitBuilder, BaseCircuitParams, CircuitBuilderStage},
         flex_gate::MultiPhaseThreadBreakPoints,
-        GateChip, GateInstructions,
+        GateChip, GateInstructions, RangeInstructions,
     },
     halo2_proofs::{
         halo2curves::bn256::{Bn256, Fr, G1Affine},
-        plonk::{verify_proof, Circuit, ProvingKey},
+        plonk::{verify_proof, Circuit, ProvingKey, VerifyingKey},
         poly::{
             commitment::{Params, ParamsProver},
             kzg::{
@@ -15,175 +15,317 @@ use halo2_base::{
                 strategy::SingleStrategy,
             },
         },
+        SerdeFormat,
     },
-    utils::{fs::gen_srs, ScalarField},
+    utils::ScalarField,
     AssignedValue,
 };
+use harness::{reference, Backend, ByteCounter};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use serde::Deserialize;
 use snark_verifier_sdk::{
     gen_pk,
-    halo2::{gen_snark_shplonk, read_snark, PoseidonTranscript},
-    NativeLoader,
+    halo2::{gen_snark_shplonk, PoseidonTranscript},
+    read_snark, NativeLoader, Snark,
 };
-use std::{env::var, fs, path::PathBuf, time::Instant};
+use std::{fs::File, path::Path};
+
+use anyhow::{anyhow, ensure, Result};
 
 #[allow(unused_imports)]
 use halo2_base::{
     Context,
     QuantumCell::{Constant, Existing, Witness},
 };
-// use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug)]
 pub struct CircuitInput {
-    pub x: usize, // field element
+    pub x: usize,
+    pub lookup_bits: Option<usize>,
 }
 
-// this algorithm takes a public input x, computes x^2 + 72, and outputs the result as public output
+/// F(n) modulo 2^64 after `n` additions, see `harness::reference::fibonacci`. Each sum is
+/// split into its low 64 bits and a carry bit. `n` and F(n) are the public output.
 fn bench_zk_circuit<F: ScalarField>(
     builder: &mut BaseCircuitBuilder<F>,
     input: CircuitInput,
     make_public: &mut Vec<AssignedValue<F>>,
 ) {
-    let k = input.x;
+    let n = input.x;
+    // range checks use the lookup table when there is one, and bit decompositions otherwise
+    let range = input.lookup_bits.map(|_| builder.range_chip());
     let ctx = builder.main(0);
-    // first we load a number `x` into as system, as a "witness"
-    // let x = ctx.load_witness(x);
-    // by default, all numbers in the system are private
-    // we can make it public like so:
-    // make_public.push(x);
     let gate = GateChip::<F>::default();
-    let mut a = ctx.load_witness(F::ZERO);
-    let mut b = ctx.load_witness(F::ONE);
-    let mut c = ctx.load_witness(F::ONE);
-    for _r in 0..k - 1 {
-        c = gate.add(ctx, Existing(a), Existing(b));
-        a = b;
-        b = c;
-    }
-    // println!("vc: {:?}", vc);
-    make_public.push(c);
-    // assert_eq!(*x.value() * x.value() + c, *out.value());
+    let two_64 = F::from_u128(1 << 64);
+    let mut a = ctx.load_constant(F::ZERO);
+    let mut b = ctx.load_constant(F::ONE);
+    for _ in 0..n {
+        let (low, carry) = a
+            .value()
+            .get_lower_64()
+            .overflowing_add(b.value().get_lower_64());
+        let c = ctx.load_witness(F::from(low));
+        let carry = ctx.load_witness(F::from(carry as u64));
+        gate.assert_bit(ctx, carry);
+        match &range {
+            Some(range) => range.range_check(ctx, c, 64),
+            None => {
+                gate.num_to_bits(ctx, c, 64);
+            }
+        }
+        // a + b = c + carry * 2^64
+        let split = gate.mul_add(ctx, carry, Constant(two_64), c);
+        let sum = gate.add(ctx, a, b);
+        ctx.constrain_equal(&split, &sum);
+        (a, b) = (b, c);
+    }
+    make_public.push(ctx.load_constant(F::from(n as u64)));
+    make_public.push(a);
 }
 
 pub fn main() {
-    for nth in [10, 100, 1000, 10_000, 100_000] {
-        let k = next_bin_log(4 * nth);
-        run_on_inputs(nth as _, k);
-    }
+    harness::main(&mut FiboHalo2::new());
 }
 
 fn next_bin_log(num: u64) -> u32 {
     (num as f64).log2().ceil() as u32
 }
 
-pub fn run_on_inputs(nth: usize, k: u32) {
-    println!("\ninput nth: {}, k: {}", nth, k);
-    let name = "fibo";
-
-    let data_path = PathBuf::from("data");
-    fs::create_dir_all(&data_path).unwrap();
-
-    let params = gen_srs(k);
-    println!("Universal trusted setup (unsafe!) available at: params/kzg_bn254_{k}.srs");
-
-    // Keygen
-    let circuit = create_circuit(CircuitBuilderStage::Keygen, None, &params, nth);
-    let pk: ProvingKey<G1Affine> = gen_pk(&params, &circuit, None);
-    let c_params = circuit.params();
-    let break_points = circuit.break_points();
-
-    // Prove
-    //warmup
-    let snark_path = data_path.join(PathBuf::from(format!("{name}.snark")));
-    if snark_path.exists() {
-        fs::remove_file(&snark_path).unwrap();
-    }
-    let pinning: (BaseCircuitParams, MultiPhaseThreadBreakPoints) =
-        (c_params.clone(), break_points.clone());
-    prove(pinning, &params, nth, &pk, &snark_path);
-
-    let snark_path = data_path.join(PathBuf::from(format!("{name}.snark")));
-    if snark_path.exists() {
-        fs::remove_file(&snark_path).unwrap();
-    }
-    let start = Instant::now();
-    let pinning: (BaseCircuitParams, MultiPhaseThreadBreakPoints) = (c_params, break_points);
-    prove(pinning, &params, nth, &pk, &snark_path);
-    let prover_time = start.elapsed();
-    println!("=== Fibo({}) Proving time: {:?}", nth, prover_time);
-    // println!("Snark written to: {snark_path:?}");
-
-    // Verify
-    let mut circuit = create_circuit(CircuitBuilderStage::Keygen, None, &params, nth);
-    let vk = pk.get_vk();
-    let snark_path = data_path.join(PathBuf::from(format!("{name}.snark")));
-    let snark = read_snark(&snark_path)
-        .unwrap_or_else(|e| panic!("Snark not found at {snark_path:?}. {e:?}"));
-
-    let verifier_params = params.verifier_params();
-    let strategy = SingleStrategy::new(&params);
-    let mut transcript = PoseidonTranscript::<NativeLoader, &[u8]>::new::<0>(&snark.proof[..]);
-    let instance = &snark.instances[0][..];
-    //warmup
-    verify_proof::<
-        KZGCommitmentScheme<Bn256>,
-        VerifierSHPLONK<'_, Bn256>,
-        _,
-        _,
-        SingleStrategy<'_, Bn256>,
-    >(
-        verifier_params,
-        &vk,
-        strategy.clone(),
-        &[&[instance]],
-        &mut transcript,
-    )
-    .unwrap();
-
-    let mut transcript = PoseidonTranscript::<NativeLoader, &[u8]>::new::<0>(&snark.proof[..]);
-    let start = Instant::now();
-    verify_proof::<
-        KZGCommitmentScheme<Bn256>,
-        VerifierSHPLONK<'_, Bn256>,
-        _,
-        _,
-        SingleStrategy<'_, Bn256>,
-    >(
-        verifier_params,
-        &vk,
-        strategy,
-        &[&[instance]],
-        &mut transcript,
-    )
-    .unwrap();
-    let verification_time = start.elapsed();
-    println!(
-        "=== Fibo({}) Snark verified successfully in {:?}",
-        nth, verification_time
-    );
-    circuit.clear();
+/// Knobs of the `[tasks.fibo.backends.halo2]` table of the runner's config.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Knobs {
+    /// The circuit gets `2^k` rows with `k = ceil(log2(rows_per_step * n))`.
+    rows_per_step: u64,
+    /// Rows of the range check lookup table are `2^lookup_bits`, no lookup table when unset.
+    lookup_bits: Option<usize>,
+    /// Rows reserved for blinding factors. This depends on the circuit itself, but we can
+    /// guess the number and change it if something breaks (9 usually works).
+    minimum_rows: usize,
 }
 
-fn prove(
-    pinning: (BaseCircuitParams, Vec<Vec<usize>>),
-    params: &ParamsKZG<Bn256>,
-    private_inputs: usize,
-    pk: &ProvingKey<G1Affine>,
-    snark_path: &PathBuf,
-) {
-    let circuit = create_circuit(
-        CircuitBuilderStage::Prover,
-        Some(pinning),
-        params,
-        private_inputs,
-    );
-    gen_snark_shplonk(params, pk, circuit, Some(snark_path));
+impl Default for Knobs {
+    fn default() -> Self {
+        Self {
+            rows_per_step: 4,
+            lookup_bits: None,
+            minimum_rows: 20,
+        }
+    }
+}
+
+struct Keys {
+    pk: ProvingKey<G1Affine>,
+    pinning: (BaseCircuitParams, MultiPhaseThreadBreakPoints),
+}
+
+pub struct FiboHalo2 {
+    knobs: Knobs,
+    params: Option<ParamsKZG<Bn256>>,
+    keys: Option<Keys>,
+    /// The key proofs are verified against: the one of `keys`, or a loaded one.
+    vk: Option<VerifyingKey<G1Affine>>,
+}
+
+impl FiboHalo2 {
+    pub fn new() -> Self {
+        Self {
+            knobs: Knobs::default(),
+            params: None,
+            keys: None,
+            vk: None,
+        }
+    }
+
+    fn params(&self) -> Result<&ParamsKZG<Bn256>> {
+        self.params
+            .as_ref()
+            .ok_or_else(|| anyhow!("setup has not been run"))
+    }
+
+    fn keys(&self) -> Result<&Keys> {
+        self.keys
+            .as_ref()
+            .ok_or_else(|| anyhow!("keygen has not been run"))
+    }
+
+    fn vk(&self) -> Result<&VerifyingKey<G1Affine>> {
+        self.vk
+            .as_ref()
+            .ok_or_else(|| anyhow!("keygen has not been run"))
+    }
+}
+
+impl Default for FiboHalo2 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for FiboHalo2 {
+    type Input = usize;
+    type Proof = Snark;
+
+    fn task(&self) -> &str {
+        "fibo"
+    }
+
+    fn name(&self) -> &str {
+        "halo2"
+    }
+
+    fn variant(&self) -> &str {
+        "kzg-shplonk"
+    }
+
+    fn versions(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("halo2_proofs", "v2023_02_02"),
+            ("halo2-base", "community-edition"),
+            ("snark-verifier-sdk", "community-edition"),
+        ]
+    }
+
+    fn configure(&mut self, params: &serde_json::Value) -> Result<()> {
+        let knobs: Knobs = harness::params(params)?;
+        ensure!(knobs.rows_per_step > 0, "rows_per_step must be at least 1");
+        self.knobs = knobs;
+        Ok(())
+    }
+
+    fn input(&self, size: usize, _seed: u64) -> usize {
+        size
+    }
+
+    fn check_size(&self, nth: usize) -> Result<()> {
+        let k = next_bin_log(self.knobs.rows_per_step * nth as u64);
+        if let Some(lookup_bits) = self.knobs.lookup_bits {
+            // we use a lookup table with 2^lookup_bits rows. Due to blinding factors, we need a little more than 2^lookup_bits rows total in our circuit
+            ensure!(
+                lookup_bits < k as usize,
+                "lookup_bits ({lookup_bits}) needs to be less than k ({k}) of size {nth}"
+            );
+        }
+        Ok(())
+    }
+
+    fn setup(&mut self, nth: usize) -> Result<()> {
+        let k = next_bin_log(self.knobs.rows_per_step * nth as u64);
+        // generated anew every time, so the phase measures the setup rather than a file read;
+        // the fixed seed gives the same parameters to the prover and a later `--verify`
+        let rng = ChaCha20Rng::from_seed(Default::default());
+        self.params = Some(ParamsKZG::setup(k, rng));
+        // stdout carries the records when there is no --output
+        eprintln!("Universal trusted setup (unsafe!) generated for k = {k}");
+        Ok(())
+    }
+
+    fn keygen(&mut self, nth: usize) -> Result<()> {
+        let params = self.params()?;
+        let circuit = create_circuit(CircuitBuilderStage::Keygen, None, params, &self.knobs, nth);
+        let pk: ProvingKey<G1Affine> = gen_pk(params, &circuit, None);
+        let pinning = (circuit.params(), circuit.break_points());
+
+        self.vk = Some(pk.get_vk().clone());
+        self.keys = Some(Keys { pk, pinning });
+        Ok(())
+    }
+
+    fn prove(&self, nth: &usize) -> Result<Snark> {
+        let params = self.params()?;
+        let keys = self.keys()?;
+        let circuit = create_circuit(
+            CircuitBuilderStage::Prover,
+            Some(keys.pinning.clone()),
+            params,
+            &self.knobs,
+            *nth,
+        );
+        // without a path gen_snark_shplonk neither writes nor reuses a snark file
+        Ok(gen_snark_shplonk(params, &keys.pk, circuit, None::<&Path>))
+    }
+
+    fn verify(&self, snark: &Snark) -> Result<()> {
+        let params = self.params()?;
+        let vk = self.vk()?;
+        let verifier_params = params.verifier_params();
+        let strategy = SingleStrategy::new(params);
+        let mut transcript = PoseidonTranscript::<NativeLoader, &[u8]>::new::<0>(&snark.proof[..]);
+        let instance = &snark.instances[0][..];
+        verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            _,
+            _,
+            SingleStrategy<'_, Bn256>,
+        >(
+            verifier_params,
+            vk,
+            strategy,
+            &[&[instance]],
+            &mut transcript,
+        )
+        .map_err(|e| anyhow!("snark verification failed: {e:?}"))
+    }
+
+    fn check_output(&self, nth: &usize, snark: &Snark) -> Result<()> {
+        // the circuit exposes n, then F(n) modulo 2^64
+        let fib = reference::fibonacci(*nth as u32);
+        let expected = [Fr::from(*nth as u64), Fr::from(fib)];
+        reference::check("n and F(n)", &expected[..], &snark.instances[0][..])
+    }
+
+    fn public_output(&self, snark: &Snark) -> Result<Vec<u8>> {
+        Ok(snark.instances[0]
+            .iter()
+            .flat_map(|x| x.to_bytes_le())
+            .collect())
+    }
+
+    fn serialize_proof(&self, snark: &Snark) -> Result<Vec<u8>> {
+        Ok(snark.proof.clone())
+    }
+
+    fn save_proof(&self, snark: &Snark, path: &Path) -> Result<()> {
+        // the snark file format of snark-verifier-sdk, see `read_snark`
+        bincode::serialize_into(File::create(path)?, snark)?;
+        Ok(())
+    }
+
+    fn load_proof(&self, path: &Path) -> Option<Result<Snark>> {
+        Some(read_snark(path).map_err(|e| anyhow!("cannot read snark {}: {e}", path.display())))
+    }
+
+    fn load_verifying_key(&mut self, nth: usize, vk: &[u8]) -> Result<()> {
+        // the constraint system comes from the circuit configured for `nth`, the fixed
+        // commitments and permutation from the saved key
+        let params = self.params()?;
+        let circuit = create_circuit(CircuitBuilderStage::Keygen, None, params, &self.knobs, nth);
+        let vk = VerifyingKey::read::<_, BaseCircuitBuilder<Fr>>(
+            &mut &vk[..],
+            SerdeFormat::RawBytes,
+            circuit.params(),
+        )?;
+        self.vk = Some(vk);
+        Ok(())
+    }
+
+    fn verifying_key(&self) -> Result<Vec<u8>> {
+        Ok(self.vk()?.to_bytes(SerdeFormat::RawBytes))
+    }
+
+    fn proving_key_size(&self) -> Result<Option<usize>> {
+        let mut counter = ByteCounter::default();
+        self.keys()?.pk.write(&mut counter, SerdeFormat::RawBytes)?;
+        Ok(Some(counter.0))
+    }
 }
 
 fn create_circuit(
     stage: CircuitBuilderStage,
     pinning: Option<(BaseCircuitParams, MultiPhaseThreadBreakPoints)>,
     params: &ParamsKZG<Bn256>,
+    knobs: &Knobs,
     private_inputs: usize,
 ) -> BaseCircuitBuilder<Fr> {
     let mut builder = BaseCircuitBuilder::from_stage(stage);
@@ -192,18 +334,10 @@ fn create_circuit(
         builder.set_break_points(break_points);
     } else {
         let k = params.k() as usize;
-        // we use env var `LOOKUP_BITS` to determine whether to use `GateThreadBuilder` or `RangeCircuitBuilder`. The difference is that the latter creates a lookup table with 2^LOOKUP_BITS rows, while the former does not.
-        let lookup_bits: Option<usize> = var("LOOKUP_BITS")
-            .map(|str| {
-                let lookup_bits = str.parse::<usize>().unwrap();
-                // we use a lookup table with 2^LOOKUP_BITS rows. Due to blinding factors, we need a little more than 2^LOOKUP_BITS rows total in our circuit
-                assert!(lookup_bits < k, "LOOKUP_BITS needs to be less than DEGREE");
-                lookup_bits
-            })
-            .ok();
+        // the `lookup_bits` knob determines whether to use `GateThreadBuilder` or `RangeCircuitBuilder`. The difference is that the latter creates a lookup table with 2^lookup_bits rows, while the former does not.
         // we initiate a "thread builder". This is what keeps track of the execution trace of our program. If not in proving mode, it also keeps track of the ZK constraints.
         builder.set_k(k);
-        if let Some(lookup_bits) = lookup_bits {
+        if let Some(lookup_bits) = knobs.lookup_bits {
             builder.set_lookup_bits(lookup_bits);
         }
         builder.set_instance_columns(1);
@@ -215,7 +349,10 @@ fn create_circuit(
     let mut assigned_instances = vec![];
     bench_zk_circuit(
         &mut builder,
-        CircuitInput { x: private_inputs },
+        CircuitInput {
+            x: private_inputs,
+            lookup_bits: knobs.lookup_bits,
+        },
         &mut assigned_instances,
     );
     if !assigned_instances.is_empty() {
@@ -229,13 +366,81 @@ fn create_circuit(
 
     if !stage.witness_gen_only() {
         // now `builder` contains the execution trace, and we are ready to actually create the circuit
-        // minimum rows is the number of rows used for blinding factors. This depends on the circuit itself, but we can guess the number and change it if something breaks (default 9 usually works)
-        let minimum_rows = var("MINIMUM_ROWS")
-            .unwrap_or_else(|_| "20".to_string())
-            .parse()
-            .unwrap();
-        builder.calculate_params(Some(minimum_rows));
+        builder.calculate_params(Some(knobs.minimum_rows));
     }
 
     builder
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A backend keyed for fibo(`n`) and its proof.
+    fn proven(n: usize) -> (FiboHalo2, Snark) {
+        let mut fibo = FiboHalo2::new();
+        fibo.setup(n).unwrap();
+        fibo.keygen(n).unwrap();
+        let snark = fibo.prove(&n).unwrap();
+        fibo.verify(&snark).unwrap();
+        fibo.check_output(&n, &snark).unwrap();
+        (fibo, snark)
+    }
+
+    #[test]
+    fn wraps_modulo_2_64() {
+        // F(94) is the first that does not fit
+        let (_, snark) = proven(100);
+        let fib = reference::fibonacci(100);
+        assert_eq!(snark.instances[0][1], Fr::from(fib));
+    }
+
+    #[test]
+    fn rejects_flipped_proof() {
+        let (fibo, mut snark) = proven(20);
+        let middle = snark.proof.len() / 2;
+        snark.proof[middle] ^= 1;
+        assert!(fibo.verify(&snark).is_err());
+    }
+
+    #[test]
+    fn rejects_swapped_instance() {
+        let (fibo, mut snark) = proven(20);
+        snark.instances[0][0] += Fr::from(1);
+        assert!(fibo.verify(&snark).is_err());
+    }
+
+    #[test]
+    fn verifies_with_a_loaded_key() {
+        let (fibo, snark) = proven(20);
+        let mut loaded = FiboHalo2::new();
+        loaded.setup(20).unwrap();
+        loaded
+            .load_verifying_key(20, &fibo.verifying_key().unwrap())
+            .unwrap();
+        loaded.verify(&snark).unwrap();
+        let (other, _) = proven(21);
+        loaded
+            .load_verifying_key(20, &other.verifying_key().unwrap())
+            .unwrap();
+        assert!(loaded.verify(&snark).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_verifying_key() {
+        let (_, snark) = proven(20);
+        // fibo(21) fits the same 2^7 rows, only the circuit differs
+        let (other, _) = proven(21);
+        assert!(other.verify(&snark).is_err());
+    }
+
+    #[test]
+    fn rejects_lookup_bits_not_below_k() {
+        let mut fibo = FiboHalo2::new();
+        fibo.configure(&serde_json::json!({ "lookup_bits": 8 }))
+            .unwrap();
+        // 4 rows per step give k = 6 for fibo(10) and k = 9 for fibo(100)
+        assert!(fibo.check_size(10).is_err());
+        fibo.check_size(100).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! The fibo workload run natively, as the baseline of the proving overhead of every other
+//! backend.
+
+use std::hint::black_box;
+
+use anyhow::Result;
+use harness::{reference, Backend};
+
+struct NativeFibo;
+
+impl Backend for NativeFibo {
+    type Input = u32;
+    /// There is no proof, only the output: `n` and `F(n)`, like the other backends commit.
+    type Proof = (u32, u64);
+
+    fn task(&self) -> &str {
+        "fibo"
+    }
+
+    fn name(&self) -> &str {
+        "native"
+    }
+
+    fn variant(&self) -> &str {
+        "rust"
+    }
+
+    fn versions(&self) -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
+
+    fn input(&self, size: usize, _seed: u64) -> u32 {
+        size as _
+    }
+
+    fn setup(&mut self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn keygen(&mut self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn execute(&self, n: &u32) -> Option<Result<()>> {
+        black_box(reference::fibonacci(black_box(*n)));
+        Some(Ok(()))
+    }
+
+    fn prove(&self, n: &u32) -> Result<(u32, u64)> {
+        // nothing to prove, this is the native run time again
+        Ok((*n, reference::fibonacci(black_box(*n))))
+    }
+
+    fn verify(&self, _output: &(u32, u64)) -> Result<()> {
+        Ok(())
+    }
+
+    fn check_output(&self, n: &u32, &(committed, fib): &(u32, u64)) -> Result<()> {
+        reference::check("n", *n, committed)?;
+        reference::check("F(n)", reference::fibonacci(*n), fib)
+    }
+
+    fn public_output(&self, &(n, fib): &(u32, u64)) -> Result<Vec<u8>> {
+        // the bytes the zkVMs commit
+        Ok([&n.to_le_bytes()[..], &fib.to_le_bytes()].concat())
+    }
+
+    fn serialize_proof(&self, _output: &(u32, u64)) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn verifying_key(&self) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn proving_key_size(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    fn program_id(&self) -> Result<String> {
+        Ok("native".into())
+    }
+}
+
+fn main() {
+    harness::main(&mut NativeFibo);
+}
@@ -1,71 +1,183 @@
-use std::time::Instant;
-
+use anyhow::{anyhow, Result};
+use harness::{reference, Backend};
+use std::{fs, path::Path};
 // These constants represent the RISC-V ELF and the image ID generated by risc0-build.
 // The ELF is used for proving and the ID is used for verification.
 use methods::{FIBO_ELF, FIBO_ID};
-use risc0_zkvm::{default_prover, ExecutorEnv};
+use risc0_zkvm::{default_executor, default_prover, sha::Digest, ExecutorEnv, Receipt};
+
+struct Risc0Fibo {
+    /// The image ID receipts are verified against: the one of the guest built into this
+    /// binary, or a loaded one.
+    image_id: Digest,
+}
+
+impl Default for Risc0Fibo {
+    fn default() -> Self {
+        Self {
+            image_id: FIBO_ID.into(),
+        }
+    }
+}
+
+impl Backend for Risc0Fibo {
+    type Input = u32;
+    type Proof = Receipt;
+
+    fn task(&self) -> &str {
+        "fibo"
+    }
+
+    fn name(&self) -> &str {
+        "risc0"
+    }
+
+    fn variant(&self) -> &str {
+        "composite"
+    }
+
+    fn versions(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("risc0-zkvm", "0.21.0")]
+    }
+
+    fn input(&self, size: usize, _seed: u64) -> u32 {
+        size as _
+    }
+
+    fn setup(&mut self, _size: usize) -> Result<()> {
+        // the risc0 circuit needs no universal parameters
+        Ok(())
+    }
+
+    fn keygen(&mut self, _size: usize) -> Result<()> {
+        // the image ID is computed by risc0-build, nothing to prepare at runtime
+        Ok(())
+    }
+
+    fn execute(&self, n: &u32) -> Option<Result<()>> {
+        let execute = || -> Result<()> {
+            let env = ExecutorEnv::builder().write(n)?.build()?;
+            default_executor().execute(env, FIBO_ELF)?;
+            Ok(())
+        };
+        Some(execute())
+    }
+
+    fn prove(&self, n: &u32) -> Result<Receipt> {
+        // An executor environment describes the configurations for the zkVM
+        // including program inputs.
+        let env = ExecutorEnv::builder().write(n)?.build()?;
+        // Obtain the default prover.
+        let prover = default_prover();
+        // Produce a receipt by proving the specified ELF binary.
+        prover.prove(env, FIBO_ELF)
+    }
+
+    fn verify(&self, receipt: &Receipt) -> Result<()> {
+        receipt
+            .verify(self.image_id)
+            .map_err(|e| anyhow!("receipt verification failed: {e:?}"))
+    }
+
+    fn check_output(&self, n: &u32, receipt: &Receipt) -> Result<()> {
+        // the guest commits n, then F(n) modulo 2^64
+        let (committed, fib): (u32, u64) = receipt.journal.decode()?;
+        reference::check("n", *n, committed)?;
+        reference::check("F(n)", reference::fibonacci(*n), fib)
+    }
+
+    fn public_output(&self, receipt: &Receipt) -> Result<Vec<u8>> {
+        Ok(receipt.journal.bytes.clone())
+    }
+
+    fn serialize_proof(&self, receipt: &Receipt) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(receipt)?)
+    }
+
+    fn load_proof(&self, path: &Path) -> Option<Result<Receipt>> {
+        let load = || -> Result<Receipt> { Ok(bincode::deserialize(&fs::read(path)?)?) };
+        Some(load())
+    }
+
+    fn load_verifying_key(&mut self, _size: usize, vk: &[u8]) -> Result<()> {
+        self.image_id = Digest::try_from(vk)?;
+        Ok(())
+    }
+
+    fn verifying_key(&self) -> Result<Vec<u8>> {
+        // receipts are verified against the image ID of the guest
+        Ok(self.image_id.as_bytes().to_vec())
+    }
+
+    fn proving_key_size(&self) -> Result<Option<usize>> {
+        // the risc0 circuit does not depend on the program, there is no proving key
+        Ok(None)
+    }
+
+    fn program_id(&self) -> Result<String> {
+        Ok(self.image_id.to_string())
+    }
+}
 
 fn main() {
-    // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
+    // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`. They go to
+    // stderr, stdout carries the records when there is no --output
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .init();
 
-    // An executor environment describes the configurations for the zkVM
-    // including program inputs.
-    // An default ExecutorEnv can be created like so:
-    // `let env = ExecutorEnv::builder().build().unwrap();`
-    // However, this `env` does not have any inputs.
-    //
-    // To add add guest input to the executor environment, use
-    // ExecutorEnvBuilder::write().
-    // To access this method, you'll need to use ExecutorEnv::builder(), which
-    // creates an ExecutorEnvBuilder. When you're done adding input, call
-    // ExecutorEnvBuilder::build().
-
-    // For example:
-    let n: u32 = 21;
-    let env: ExecutorEnv = ExecutorEnv::builder().write(&n).unwrap().build().unwrap();
-
-    // Obtain the default prover.
-    let prover = default_prover();
-
-    // Produce a receipt by proving the specified ELF binary.
-    let receipt = prover.prove(env, FIBO_ELF).unwrap();
-
-    // TODO: Implement code for retrieving receipt journal here.
-
-    // For example:
-    let output: u32 = receipt.journal.decode().unwrap();
-    // println!("output: {}", output);
-    assert_eq!(output, 10946);
-
-    // The receipt was verified at the end of proving, but the below code is an
-    // example of how someone else could verify this receipt.
-    receipt.verify(FIBO_ID).unwrap();
-    for n in [10, 100, 1000, 10_000, 100_000] {
-        let env: ExecutorEnv = ExecutorEnv::builder().write(&n).unwrap().build().unwrap();
-        // Obtain the default prover.
-        let prover = default_prover();
+    harness::main(&mut Risc0Fibo::default());
+}
 
-        //warmup
-        let receipt = prover.prove(env, FIBO_ELF).unwrap();
-        receipt.verify(FIBO_ID).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        //benchmark running
-        let env: ExecutorEnv = ExecutorEnv::builder().write(&n).unwrap().build().unwrap();
-        let t = Instant::now();
-        // Produce a receipt by proving the specified ELF binary.
-        let receipt = prover.prove(env, FIBO_ELF).unwrap();
-        println!("=== Fibo({}) Proving time: {:?}", n, t.elapsed());
+    #[test]
+    fn proves_fibo_21() {
+        let fibo = Risc0Fibo::default();
+        let receipt = fibo.prove(&21).unwrap();
+        let output: (u32, u64) = receipt.journal.decode().unwrap();
+        assert_eq!(output, (21, 10946));
+        fibo.verify(&receipt).unwrap();
+    }
+
+    #[test]
+    fn rejects_flipped_seal() {
+        let receipt = Risc0Fibo::default().prove(&21).unwrap();
+        let mut bytes = bincode::serialize(&receipt).unwrap();
+        // the seal makes up nearly all of a serialized receipt
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        let receipt: Receipt = bincode::deserialize(&bytes).unwrap();
+        assert!(receipt.verify(FIBO_ID).is_err());
+    }
 
-        // For example:
-        let _output: u32 = receipt.journal.decode().unwrap();
+    #[test]
+    fn rejects_swapped_journal() {
+        let mut receipt = Risc0Fibo::default().prove(&21).unwrap();
+        receipt.journal.bytes[0] ^= 1;
+        assert!(receipt.verify(FIBO_ID).is_err());
+    }
+
+    #[test]
+    fn verifies_with_a_loaded_image_id() {
+        let receipt = Risc0Fibo::default().prove(&21).unwrap();
+        let mut loaded = Risc0Fibo::default();
+        let mut image_id = Digest::from(FIBO_ID).as_bytes().to_vec();
+        loaded.load_verifying_key(21, &image_id).unwrap();
+        loaded.verify(&receipt).unwrap();
+        image_id[0] ^= 1;
+        loaded.load_verifying_key(21, &image_id).unwrap();
+        assert!(loaded.verify(&receipt).is_err());
+    }
 
-        // The receipt was verified at the end of proving, but the below code is an
-        // example of how someone else could verify this receipt.
-        let t = Instant::now();
-        receipt.verify(FIBO_ID).unwrap();
-        println!("=== Fibo({}) Verifying time: {:?}", n, t.elapsed());
+    #[test]
+    fn rejects_wrong_image_id() {
+        let receipt = Risc0Fibo::default().prove(&21).unwrap();
+        let mut image_id = FIBO_ID;
+        image_id[0] ^= 1;
+        assert!(receipt.verify(image_id).is_err());
     }
 }
@@ -21,11 +21,10 @@ fn main() {
     println!("generated proof");
 
     // Read and verify the output.
-    let _ = proof.public_values.read::<u32>();
-    let a = proof.public_values.read::<u32>();
-    let b = proof.public_values.read::<u32>();
-    println!("a: {}", a);
-    println!("b: {}", b);
+    // the program commits n, then F(n) modulo 2^64
+    let n = proof.public_values.read::<u32>();
+    let fib = proof.public_values.read::<u64>();
+    println!("n: {}, F(n): {}", n, fib);
 
     // Verify proof and public values
     client
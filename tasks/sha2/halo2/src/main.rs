@@ -1,124 +1,524 @@
-use halo2_proofs::{
-    circuit::{Layouter, SimpleFloorPlanner, Value},
-    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ConstraintSystem, Error},
-    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
-};
-use halo2curves::pasta::{pallas, EqAffine};
-use rand::{rngs::OsRng, Rng};
-
-use std::time::Instant;
+mod select;
 
-use halo2_gadgets::sha256::{BlockWord, Sha256, Table16Chip, Table16Config, BLOCK_SIZE};
-
-use halo2_proofs::{
-    poly::{
-        commitment::ParamsProver,
-        ipa::{
-            commitment::{IPACommitmentScheme, ParamsIPA},
-            multiopen::{ProverIPA, VerifierIPA},
-            strategy::AccumulatorStrategy,
+use anyhow::{anyhow, ensure, Result};
+use halo2_base::{
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        halo2curves::bn256::{Bn256, Fr, G1Affine},
+        plonk::{
+            verify_proof, Advice, Circuit, Column, ConstraintSystem, Error, Expression, Instance,
+            ProvingKey, VerifyingKey,
+        },
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::{KZGCommitmentScheme, ParamsKZG},
+                multiopen::VerifierSHPLONK,
+                strategy::SingleStrategy,
+            },
+            Rotation,
         },
+        SerdeFormat,
     },
-    transcript::{TranscriptReadBuffer, TranscriptWriterBuffer},
+    utils::ScalarField,
 };
+use harness::{Backend, ByteCounter};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use serde::Deserialize;
+use snark_verifier_sdk::{
+    gen_pk,
+    halo2::{gen_snark_shplonk, PoseidonTranscript},
+    read_snark, CircuitExt, NativeLoader, Snark,
+};
+use std::{fs::File, path::Path};
+use zkevm_hashes::sha256::vanilla::columns::Sha256CircuitConfig;
+
+use select::SelectConfig;
+
+/// Bytes in a SHA-256 message block.
+const BLOCK_BYTES: usize = 64;
+
+/// Rows the zkevm-hashes circuit takes for every block.
+const ROWS_PER_BLOCK: usize = 72;
+
+/// Blocks of `len` input bytes after the standard padding: a 0x80 byte and the 8 bytes of
+/// the length in bits.
+fn blocks(len: usize) -> usize {
+    (len + 8) / BLOCK_BYTES + 1
+}
+
+/// Words of a SHA-256 digest.
+const DIGEST_WORDS: usize = 8;
+
+/// The digest of `input` as the instances of the circuit: its eight big-endian 32-bit words.
+fn digest_instances(input: &[u8]) -> Vec<Fr> {
+    let digest = harness::reference::sha256(input);
+    digest
+        .chunks(4)
+        .map(|word| Fr::from(u32::from_be_bytes(word.try_into().unwrap()) as u64))
+        .collect()
+}
+
+/// Knobs of the `[tasks.sha2.backends.halo2]` table of the runner's config.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Knobs {
+    /// Circuit capacity and degree by input size, the first entry covering the size is used.
+    k: Vec<Degree>,
+}
 
+/// A circuit hashing any input of at most `up_to` bytes, with 2^`k` rows.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Degree {
+    up_to: usize,
+    k: u32,
+}
+
+impl Degree {
+    /// Blocks of the padded `up_to` bytes input, the longest one.
+    fn capacity(&self) -> usize {
+        blocks(self.up_to)
+    }
+}
+
+impl Default for Knobs {
+    fn default() -> Self {
+        // one and two blocks for the smallest sizes, so they do not pay for a larger capacity
+        let k = [(55, 8), (119, 9), (1000, 11), (10_000, 14), (100_000, 17)]
+            .map(|(up_to, k)| Degree { up_to, k })
+            .to_vec();
+        Self { k }
+    }
+}
+
+impl Knobs {
+    /// The circuit fitting `n` input bytes.
+    fn degree_for(&self, n: usize) -> Result<&Degree> {
+        self.k
+            .iter()
+            .find(|d| n <= d.up_to)
+            .ok_or_else(|| anyhow!("no circuit degree `k` configured for size {n}"))
+    }
+}
+
+/// The zkevm-hashes SHA-256 circuit over `capacity` blocks, with the digest words of `input`
+/// as its instances. Dummy inputs fill the blocks `input` leaves free, so the layout only
+/// depends on the capacity.
 #[derive(Clone)]
-struct MyCircuit {
+struct Sha2Circuit {
     input: Vec<u8>,
+    capacity: usize,
 }
 
-impl Circuit<pallas::Base> for MyCircuit {
-    type Config = Table16Config;
+/// The SHA-256 columns, the columns selecting the digest of `input` among the blocks, and
+/// the words of the selected digest, copied to the instance column.
+#[derive(Clone)]
+struct Sha2Config {
+    sha: Sha256CircuitConfig<Fr>,
+    select: SelectConfig,
+    words: [Column<Advice>; DIGEST_WORDS],
+    instance: Column<Instance>,
+}
+
+impl Circuit<Fr> for Sha2Circuit {
+    type Config = Sha2Config;
     type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
 
     fn without_witnesses(&self) -> Self {
-        Self { input: vec![] }
+        self.clone()
     }
 
-    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
-        Table16Chip::configure(meta)
-    }
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Sha2Config {
+        let sha = Sha256CircuitConfig::new(meta);
+        let select = SelectConfig::configure(meta);
+        let words = [(); DIGEST_WORDS].map(|_| {
+            let column = meta.advice_column();
+            meta.enable_equality(column);
+            column
+        });
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
 
-    fn synthesize(
-        &self,
-        config: Self::Config,
-        mut layouter: impl Layouter<pallas::Base>,
-    ) -> Result<(), Error> {
-        Table16Chip::load(config.clone(), &mut layouter)?;
-        let table16_chip = Table16Chip::construct(config);
+        // The words are not range checked: they are copied from the instances, which the
+        // verifier sets to the 32-bit words of the digest it expects. Their sums are then
+        // below 2^128, so each can only equal the 128-bit half it is compared with.
+        meta.create_gate("the words make up the selected digest", |meta| {
+            let q = meta.query_selector(select.q_last);
+            let shift = Expression::Constant(Fr::from(1 << 32));
+            select
+                .digest
+                .iter()
+                .zip(words.chunks(DIGEST_WORDS / 2))
+                .map(|(&digest, words)| {
+                    let half = words
+                        .iter()
+                        .fold(Expression::Constant(Fr::from(0)), |acc, &word| {
+                            acc * shift.clone() + meta.query_advice(word, Rotation::cur())
+                        });
+                    q.clone() * (meta.query_advice(digest, Rotation::cur()) - half)
+                })
+                .collect::<Vec<_>>()
+        });
 
-        // Convert input bytes to BlockWords
-        let mut input_blocks = Vec::new();
-        for chunk in self.input.chunks(4) {
-            let mut block = [0u8; 4];
-            for (i, &byte) in chunk.iter().enumerate() {
-                block[i] = byte;
-            }
-            input_blocks.push(BlockWord(Value::known(u32::from_be_bytes(block))));
+        Sha2Config {
+            sha,
+            select,
+            words,
+            instance,
         }
+    }
 
-        // Pad the input to a multiple of BLOCK_SIZE
-        let padded_len = ((self.input.len() + 8) / BLOCK_SIZE + 1) * BLOCK_SIZE;
-        while input_blocks.len() < padded_len / 4 {
-            input_blocks.push(BlockWord(Value::known(0)));
+    fn synthesize(&self, config: Sha2Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        let words = layouter.assign_region(
+            || "sha256",
+            |mut region| {
+                let blocks = config.sha.multi_sha256(
+                    &mut region,
+                    vec![self.input.clone()],
+                    Some(self.capacity),
+                );
+                let outputs = blocks
+                    .iter()
+                    .map(|block| (block.is_final(), [block.output().hi(), block.output().lo()]));
+                let last = config.select.assign(&mut region, outputs)?;
+                Ok(config
+                    .words
+                    .iter()
+                    .zip(digest_instances(&self.input))
+                    .map(|(column, word)| {
+                        region
+                            .assign_advice(*column, last, Value::known(word))
+                            .cell()
+                    })
+                    .collect::<Vec<_>>())
+            },
+        )?;
+        for (row, cell) in words.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.instance, row);
         }
+        Ok(())
+    }
+}
+
+impl CircuitExt<Fr> for Sha2Circuit {
+    fn num_instance(&self) -> Vec<usize> {
+        vec![DIGEST_WORDS]
+    }
+
+    fn instances(&self) -> Vec<Vec<Fr>> {
+        vec![digest_instances(&self.input)]
+    }
+}
+
+struct Keys {
+    /// Blocks the circuit hashes at most.
+    capacity: usize,
+    pk: ProvingKey<G1Affine>,
+}
+
+struct Halo2Sha2 {
+    knobs: Knobs,
+    params: Option<ParamsKZG<Bn256>>,
+    keys: Option<Keys>,
+    /// The key proofs are verified against: the one of `keys`, or a loaded one.
+    vk: Option<VerifyingKey<G1Affine>>,
+}
+
+impl Halo2Sha2 {
+    fn params(&self) -> Result<&ParamsKZG<Bn256>> {
+        self.params
+            .as_ref()
+            .ok_or_else(|| anyhow!("setup has not been run"))
+    }
 
-        // Append the length of the message as a 64-bit big-endian integer
-        let bit_len = (self.input.len() * 8) as u64;
-        let len_bytes = bit_len.to_be_bytes();
-        for &byte in &len_bytes {
-            input_blocks.push(BlockWord(Value::known(byte as u32)));
+    fn keys(&self) -> Result<&Keys> {
+        self.keys
+            .as_ref()
+            .ok_or_else(|| anyhow!("keygen has not been run"))
+    }
+
+    fn vk(&self) -> Result<&VerifyingKey<G1Affine>> {
+        self.vk
+            .as_ref()
+            .ok_or_else(|| anyhow!("keygen has not been run"))
+    }
+}
+
+impl Backend for Halo2Sha2 {
+    type Input = Vec<u8>;
+    type Proof = Snark;
+
+    fn task(&self) -> &str {
+        "sha2"
+    }
+
+    fn name(&self) -> &str {
+        "halo2"
+    }
+
+    fn variant(&self) -> &str {
+        "kzg-shplonk"
+    }
+
+    fn versions(&self) -> Vec<(&'static str, &'static str)> {
+        // halo2-base proves with the halo2-axiom fork of halo2_proofs
+        vec![
+            ("halo2-axiom", "0.4"),
+            ("halo2-base", "community-edition"),
+            ("zkevm-hashes", "community-edition"),
+            ("snark-verifier-sdk", "community-edition"),
+        ]
+    }
+
+    fn configure(&mut self, params: &serde_json::Value) -> Result<()> {
+        let knobs: Knobs = harness::params(params)?;
+        ensure!(
+            knobs.k.windows(2).all(|w| w[0].up_to < w[1].up_to),
+            "k must be sorted by increasing up_to"
+        );
+        for d in &knobs.k {
+            // the blocks, and a little room for the blinding rows
+            ensure!(
+                (d.capacity() + 1) * ROWS_PER_BLOCK <= 1 << d.k,
+                "k = {} is too small for the {} blocks of up_to = {}",
+                d.k,
+                d.capacity(),
+                d.up_to
+            );
         }
+        self.knobs = knobs;
+        Ok(())
+    }
+
+    fn check_size(&self, size: usize) -> Result<()> {
+        self.knobs.degree_for(size).map(|_| ())
+    }
 
-        Sha256::digest(table16_chip, layouter.namespace(|| "sha256"), &input_blocks)?;
+    fn input(&self, size: usize, seed: u64) -> Vec<u8> {
+        harness::corpus::bytes(self.task(), seed, size)
+    }
 
+    fn setup(&mut self, size: usize) -> Result<()> {
+        let k = self.knobs.degree_for(size)?.k;
+        // generated anew every time, so the phase measures the setup rather than a file read;
+        // the fixed seed gives the same parameters to the prover and a later `--verify`
+        let rng = ChaCha20Rng::from_seed(Default::default());
+        self.params = Some(ParamsKZG::setup(k, rng));
         Ok(())
     }
-}
 
-fn main() {
-    let mut rng = rand::thread_rng();
-    for (k, n) in [(17, 10), (17, 100), (17, 1000), (19, 10_000), (22, 100_000)] {
-        let input_data: Vec<u8> = (0..n).map(|_| rng.gen()).collect();
-        // Initialize the polynomial commitment parameters
-        let params: ParamsIPA<EqAffine> = ParamsIPA::new(k); //ParamsIPA::new is TERRIBLE!
-
-        let circuit = MyCircuit {
-            input: input_data.clone(),
+    fn keygen(&mut self, size: usize) -> Result<()> {
+        let params = self.params()?;
+        // the layout only depends on the capacity, so every size up to `up_to` shares the keys
+        let capacity = self.knobs.degree_for(size)?.capacity();
+        let circuit = Sha2Circuit {
+            input: vec![],
+            capacity,
         };
-        // Initialize the proving key
-        let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
-        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("keygen_pk should not fail");
-
-        // Create a proof
-        let t = Instant::now();
-        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
-        create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
-            &params,
-            &pk,
-            &[circuit.clone()],
-            &[],
-            OsRng,
-            &mut transcript,
-        )
-        .expect("proof generation should not fail");
-        let proof: Vec<u8> = transcript.finalize();
-        println!("=== sha2({}) Proving time: {:?}", n, t.elapsed());
-
-        // Verify the proof
-        let t = Instant::now();
-        use halo2_proofs::poly::VerificationStrategy;
-        let strategy = AccumulatorStrategy::new(&params);
-        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
-        let strategy = verify_proof::<IPACommitmentScheme<_>, VerifierIPA<_>, _, _, _>(
-            &params,
-            &vk,
+        let pk = gen_pk(params, &circuit, None);
+
+        self.vk = Some(pk.get_vk().clone());
+        self.keys = Some(Keys { capacity, pk });
+        Ok(())
+    }
+
+    fn prove(&self, input: &Vec<u8>) -> Result<Snark> {
+        let params = self.params()?;
+        let keys = self.keys()?;
+        ensure!(
+            blocks(input.len()) <= keys.capacity,
+            "{} bytes do not fit the {} blocks of the circuit",
+            input.len(),
+            keys.capacity
+        );
+        let circuit = Sha2Circuit {
+            input: input.clone(),
+            capacity: keys.capacity,
+        };
+        Ok(gen_snark_shplonk(params, &keys.pk, circuit, None::<&Path>))
+    }
+
+    fn verify(&self, snark: &Snark) -> Result<()> {
+        let params = self.params()?;
+        let vk = self.vk()?;
+        let verifier_params = params.verifier_params();
+        let strategy = SingleStrategy::new(params);
+        let mut transcript = PoseidonTranscript::<NativeLoader, &[u8]>::new::<0>(&snark.proof[..]);
+        let instance = &snark.instances[0][..];
+        verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            _,
+            _,
+            SingleStrategy<'_, Bn256>,
+        >(
+            verifier_params,
+            vk,
             strategy,
-            &[],
+            &[&[instance]],
             &mut transcript,
         )
-        .unwrap();
-        assert!(strategy.finalize());
-        println!("=== sha2({}) Verifying time: {:?}", n, t.elapsed());
+        .map_err(|e| anyhow!("snark verification failed: {e:?}"))
+    }
+
+    fn check_output(&self, input: &Vec<u8>, snark: &Snark) -> Result<()> {
+        // the instances are the big-endian words of the digest
+        let expected = digest_instances(input);
+        harness::reference::check("digest", &expected[..], &snark.instances[0][..])
+    }
+
+    fn public_output(&self, snark: &Snark) -> Result<Vec<u8>> {
+        Ok(snark.instances[0]
+            .iter()
+            .flat_map(|x| x.to_bytes_le())
+            .collect())
+    }
+
+    fn serialize_proof(&self, snark: &Snark) -> Result<Vec<u8>> {
+        Ok(snark.proof.clone())
+    }
+
+    fn save_proof(&self, snark: &Snark, path: &Path) -> Result<()> {
+        // the snark file format of snark-verifier-sdk, see `read_snark`
+        bincode::serialize_into(File::create(path)?, snark)?;
+        Ok(())
+    }
+
+    fn load_proof(&self, path: &Path) -> Option<Result<Snark>> {
+        Some(read_snark(path).map_err(|e| anyhow!("cannot read snark {}: {e}", path.display())))
+    }
+
+    fn load_verifying_key(&mut self, _size: usize, vk: &[u8]) -> Result<()> {
+        // the constraint system comes from `configure`, the fixed commitments and
+        // permutation of the capacity from the saved key
+        let vk = VerifyingKey::read::<_, Sha2Circuit>(&mut &vk[..], SerdeFormat::RawBytes, ())?;
+        self.vk = Some(vk);
+        Ok(())
+    }
+
+    fn verifying_key(&self) -> Result<Vec<u8>> {
+        Ok(self.vk()?.to_bytes(SerdeFormat::RawBytes))
+    }
+
+    fn proving_key_size(&self) -> Result<Option<usize>> {
+        let mut counter = ByteCounter::default();
+        self.keys()?.pk.write(&mut counter, SerdeFormat::RawBytes)?;
+        Ok(Some(counter.0))
+    }
+}
+
+fn main() {
+    harness::main(&mut Halo2Sha2 {
+        knobs: Knobs::default(),
+        params: None,
+        keys: None,
+        vk: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::halo2_proofs::dev::MockProver;
+    use sha2::{Digest, Sha256};
+
+    /// A backend with the default circuits: one block up to 55 bytes, two up to 119.
+    fn backend() -> Halo2Sha2 {
+        Halo2Sha2 {
+            knobs: Knobs::default(),
+            params: None,
+            keys: None,
+            vk: None,
+        }
+    }
+
+    /// A backend keyed for `size` input bytes and a proof of such an input.
+    fn proven(size: usize) -> (Halo2Sha2, Snark) {
+        let mut sha2 = backend();
+        sha2.setup(size).unwrap();
+        sha2.keygen(size).unwrap();
+        let input = sha2.input(size, 0);
+        let snark = sha2.prove(&input).unwrap();
+        sha2.verify(&snark).unwrap();
+        sha2.check_output(&input, &snark).unwrap();
+        (sha2, snark)
+    }
+
+    #[test]
+    fn padding_matches_sha2() {
+        // around the lengths where the padding takes another block
+        for len in [0, 1, 55, 56, 63, 64, 119, 120] {
+            // the 0x80 byte and the 8 length bytes, rounded up to whole blocks
+            assert_eq!(blocks(len), (len + 9).div_ceil(BLOCK_BYTES), "{len} bytes");
+            let input = vec![0xab; len];
+            let words = Sha256::digest(&input)
+                .chunks(4)
+                .map(|word| Fr::from(u32::from_be_bytes(word.try_into().unwrap()) as u64))
+                .collect();
+            let circuit = Sha2Circuit {
+                input,
+                capacity: blocks(len),
+            };
+            MockProver::run(9, &circuit, vec![words])
+                .unwrap()
+                .assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn one_key_for_every_length() {
+        let mut sha2 = backend();
+        sha2.setup(119).unwrap();
+        sha2.keygen(119).unwrap();
+        for len in [0, 55, 56, 119] {
+            let input = sha2.input(len, 0);
+            let snark = sha2.prove(&input).unwrap();
+            sha2.verify(&snark).unwrap();
+            sha2.check_output(&input, &snark).unwrap();
+        }
+        assert!(sha2.prove(&sha2.input(120, 0)).is_err());
+    }
+
+    #[test]
+    fn verifies_with_a_loaded_key() {
+        let (sha2, snark) = proven(10);
+        let mut loaded = backend();
+        loaded.setup(10).unwrap();
+        loaded
+            .load_verifying_key(10, &sha2.verifying_key().unwrap())
+            .unwrap();
+        loaded.verify(&snark).unwrap();
+    }
+
+    #[test]
+    fn rejects_flipped_proof() {
+        let (sha2, mut snark) = proven(10);
+        let middle = snark.proof.len() / 2;
+        snark.proof[middle] ^= 1;
+        assert!(sha2.verify(&snark).is_err());
+    }
+
+    #[test]
+    fn rejects_swapped_instance() {
+        let (sha2, mut snark) = proven(10);
+        snark.instances[0][0] += Fr::from(1);
+        assert!(sha2.verify(&snark).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_verifying_key() {
+        let (_, snark) = proven(10);
+        // 100 bytes take the circuit of two blocks
+        let (other, _) = proven(100);
+        assert!(other.verify(&snark).is_err());
+    }
+
+    #[test]
+    fn rejects_sizes_beyond_the_k_table() {
+        let sha2 = backend();
+        sha2.check_size(100_000).unwrap();
+        assert!(sha2.check_size(100_001).is_err());
     }
 }
@@ -0,0 +1,129 @@
+//! Picks the digest of the input out of the fixed number of blocks the circuit hashes. The
+//! input length is a witness, so the layout only depends on the capacity and one key serves
+//! every input that fits.
+
+use halo2_base::halo2_proofs::{
+    circuit::{AssignedCell, Region, Value},
+    halo2curves::bn256::Fr,
+    plonk::{Advice, Assigned, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+/// A SHA-256 cell as zkevm-hashes assigns it.
+pub type ShaCell<'v> = AssignedCell<&'v Assigned<Fr>, Fr>;
+
+/// Columns selecting the output of the first block marked final, the last block of the
+/// input. A row per block carries its `is_final` flag and `output`, and the digest selected
+/// so far; the row after the blocks holds the selected digest.
+#[derive(Clone)]
+pub struct SelectConfig {
+    q_first: Selector,
+    q_block: Selector,
+    /// Enabled on the row after the blocks.
+    pub q_last: Selector,
+    is_final: Column<Advice>,
+    output: [Column<Advice>; 2],
+    /// Whether a final block came before this row.
+    seen: Column<Advice>,
+    /// High and low 128 bits of the digest selected so far.
+    pub digest: [Column<Advice>; 2],
+}
+
+impl SelectConfig {
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> Self {
+        let [is_final, hi, lo, seen, digest_hi, digest_lo] = [(); 6].map(|_| {
+            let column = meta.advice_column();
+            meta.enable_equality(column);
+            column
+        });
+        let (q_first, q_block, q_last) = (meta.selector(), meta.selector(), meta.selector());
+        let one = || Expression::Constant(Fr::from(1));
+
+        meta.create_gate("nothing selected before the first block", |meta| {
+            let q = meta.query_selector(q_first);
+            [seen, digest_hi, digest_lo]
+                .map(|column| q.clone() * meta.query_advice(column, Rotation::cur()))
+        });
+        meta.create_gate("select the output of the first final block", |meta| {
+            let q = meta.query_selector(q_block);
+            let is_final = meta.query_advice(is_final, Rotation::cur());
+            let seen_now = meta.query_advice(seen, Rotation::cur());
+            let seen_next = meta.query_advice(seen, Rotation::next());
+            let first = is_final.clone() * (one() - seen_now.clone());
+            let mut constraints = vec![
+                q.clone() * is_final.clone() * (one() - is_final),
+                q.clone() * (seen_next - seen_now - first.clone()),
+            ];
+            for (output, digest) in [(hi, digest_hi), (lo, digest_lo)] {
+                let output = meta.query_advice(output, Rotation::cur());
+                let now = meta.query_advice(digest, Rotation::cur());
+                let next = meta.query_advice(digest, Rotation::next());
+                constraints.push(q.clone() * (next - now - first.clone() * output));
+            }
+            constraints
+        });
+        meta.create_gate("the input has a final block", |meta| {
+            let q = meta.query_selector(q_last);
+            vec![q * (meta.query_advice(seen, Rotation::cur()) - one())]
+        });
+
+        Self {
+            q_first,
+            q_block,
+            q_last,
+            is_final,
+            output: [hi, lo],
+            seen,
+            digest: [digest_hi, digest_lo],
+        }
+    }
+
+    /// Copies the `is_final` flag and the high and low output of every block into the rows
+    /// from 0, and assigns the selected digest to the row after them. Returns that row.
+    pub fn assign<'a, 'v: 'a>(
+        &self,
+        region: &mut Region<'_, Fr>,
+        blocks: impl IntoIterator<Item = (&'a ShaCell<'v>, [&'a ShaCell<'v>; 2])>,
+    ) -> Result<usize, Error> {
+        let zero = || Value::known(Fr::from(0));
+        let mut seen = zero();
+        let mut digest = [zero(), zero()];
+        let mut row = 0;
+        self.q_first.enable(region, 0)?;
+        for (is_final, output) in blocks {
+            self.q_block.enable(region, row)?;
+            region.assign_advice(self.seen, row, seen);
+            for (column, value) in self.digest.iter().zip(digest) {
+                region.assign_advice(*column, row, value);
+            }
+            let is_final = copy(region, is_final, self.is_final, row);
+            let first = is_final * (Value::known(Fr::from(1)) - seen);
+            for (i, cell) in output.into_iter().enumerate() {
+                let value = copy(region, cell, self.output[i], row);
+                digest[i] = digest[i] + first * value;
+            }
+            seen = seen + first;
+            row += 1;
+        }
+
+        self.q_last.enable(region, row)?;
+        region.assign_advice(self.seen, row, seen);
+        for (column, value) in self.digest.iter().zip(digest) {
+            region.assign_advice(*column, row, value);
+        }
+        Ok(row)
+    }
+}
+
+/// Copies the value of a SHA-256 `cell` into `column` of our own rows.
+fn copy(
+    region: &mut Region<'_, Fr>,
+    cell: &ShaCell,
+    column: Column<Advice>,
+    row: usize,
+) -> Value<Fr> {
+    let value = cell.value().map(|v| v.evaluate());
+    let copied = region.assign_advice(column, row, value);
+    region.constrain_equal(cell.cell(), copied.cell());
+    value
+}
@@ -0,0 +1,86 @@
+//! The sha2 workload run natively, as the baseline of the proving overhead of every other
+//! backend.
+
+use std::hint::black_box;
+
+use anyhow::Result;
+use harness::{reference, Backend};
+use sha2::{Digest, Sha256};
+
+struct NativeSha2;
+
+impl Backend for NativeSha2 {
+    type Input = Vec<u8>;
+    /// There is no proof, only the digest.
+    type Proof = [u8; 32];
+
+    fn task(&self) -> &str {
+        "sha2"
+    }
+
+    fn name(&self) -> &str {
+        "native"
+    }
+
+    fn variant(&self) -> &str {
+        "rust"
+    }
+
+    fn versions(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("sha2", "0.10")]
+    }
+
+    fn input(&self, size: usize, seed: u64) -> Vec<u8> {
+        harness::corpus::bytes(self.task(), seed, size)
+    }
+
+    fn setup(&mut self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn keygen(&mut self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn execute(&self, input: &Vec<u8>) -> Option<Result<()>> {
+        black_box(Sha256::digest(black_box(input)));
+        Some(Ok(()))
+    }
+
+    fn prove(&self, input: &Vec<u8>) -> Result<[u8; 32]> {
+        // nothing to prove, this is the native run time again
+        Ok(Sha256::digest(black_box(input)).into())
+    }
+
+    fn verify(&self, _digest: &[u8; 32]) -> Result<()> {
+        Ok(())
+    }
+
+    fn check_output(&self, input: &Vec<u8>, digest: &[u8; 32]) -> Result<()> {
+        reference::check("digest", reference::sha256(input), *digest)
+    }
+
+    fn public_output(&self, digest: &[u8; 32]) -> Result<Vec<u8>> {
+        Ok(digest.to_vec())
+    }
+
+    fn serialize_proof(&self, _digest: &[u8; 32]) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn verifying_key(&self) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn proving_key_size(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    fn program_id(&self) -> Result<String> {
+        Ok("native".into())
+    }
+}
+
+fn main() {
+    harness::main(&mut NativeSha2);
+}
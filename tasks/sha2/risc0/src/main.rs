@@ -12,60 +12,141 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::time::Instant;
-
-use rand::Rng;
-use risc0_zkvm::{default_prover, sha::Digest, ExecutorEnv};
+use anyhow::{anyhow, Result};
+use harness::{reference, Backend};
+use risc0_zkvm::{default_executor, default_prover, sha::Digest, ExecutorEnv, Receipt};
 use sha_methods::{HASH_ELF, HASH_ID};
+use std::{fs, path::Path};
 
-fn bench_hash(input: Vec<u8>, n: usize) {
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .unwrap()
-        .build()
-        .unwrap();
-
-    let elf = HASH_ELF;
-    let id = HASH_ID;
-
-    let prover = default_prover();
-    let receipt = prover.prove(env, elf).unwrap();
-    receipt.verify(id).expect("receipt verification failed");
-
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .unwrap()
-        .build()
-        .unwrap();
-    let t = Instant::now();
-    let prover = default_prover();
-    let receipt = prover.prove(env, elf).unwrap();
-    println!("=== sha2({}) Proving time: {:?}", n, t.elapsed());
-
-    let _h: Digest = receipt.journal.decode().unwrap();
-    println!("h: {:?}", _h);
-
-    let t = Instant::now();
-    receipt.verify(id).expect("receipt verification failed");
-    println!("=== sha2({}) Proving time: {:?}", n, t.elapsed());
+struct Risc0Sha2 {
+    /// The image ID receipts are verified against: the one of the guest built into this
+    /// binary, or a loaded one.
+    image_id: Digest,
 }
 
-fn main() {
-    let mut rng = rand::thread_rng();
-    for n in [10, 100, 1000, 10_000, 100_000] {
-        let input: Vec<u8> = (0..n).map(|_| rng.gen()).collect();
-        bench_hash(input, n);
+impl Default for Risc0Sha2 {
+    fn default() -> Self {
+        Self {
+            image_id: HASH_ID.into(),
+        }
     }
 }
 
+impl Backend for Risc0Sha2 {
+    type Input = Vec<u8>;
+    type Proof = Receipt;
+
+    fn task(&self) -> &str {
+        "sha2"
+    }
+
+    fn name(&self) -> &str {
+        "risc0"
+    }
+
+    fn variant(&self) -> &str {
+        "composite"
+    }
+
+    fn versions(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("risc0-zkvm", "0.21.0")]
+    }
+
+    fn input(&self, size: usize, seed: u64) -> Vec<u8> {
+        harness::corpus::bytes(self.task(), seed, size)
+    }
+
+    fn setup(&mut self, _size: usize) -> Result<()> {
+        // the risc0 circuit needs no universal parameters
+        Ok(())
+    }
+
+    fn keygen(&mut self, _size: usize) -> Result<()> {
+        // the image ID is computed by risc0-build, nothing to prepare at runtime
+        Ok(())
+    }
+
+    fn execute(&self, input: &Vec<u8>) -> Option<Result<()>> {
+        let execute = || -> Result<()> {
+            let env = ExecutorEnv::builder().write(input)?.build()?;
+            default_executor().execute(env, HASH_ELF)?;
+            Ok(())
+        };
+        Some(execute())
+    }
+
+    fn prove(&self, input: &Vec<u8>) -> Result<Receipt> {
+        let env = ExecutorEnv::builder().write(input)?.build()?;
+        let prover = default_prover();
+        prover.prove(env, HASH_ELF)
+    }
+
+    fn verify(&self, receipt: &Receipt) -> Result<()> {
+        receipt
+            .verify(self.image_id)
+            .map_err(|e| anyhow!("receipt verification failed: {e:?}"))
+    }
+
+    fn check_output(&self, input: &Vec<u8>, receipt: &Receipt) -> Result<()> {
+        let digest: Digest = receipt.journal.decode()?;
+        reference::check(
+            "digest",
+            reference::sha256(input).as_slice(),
+            digest.as_bytes(),
+        )
+    }
+
+    fn public_output(&self, receipt: &Receipt) -> Result<Vec<u8>> {
+        Ok(receipt.journal.bytes.clone())
+    }
+
+    fn serialize_proof(&self, receipt: &Receipt) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(receipt)?)
+    }
+
+    fn load_proof(&self, path: &Path) -> Option<Result<Receipt>> {
+        let load = || -> Result<Receipt> { Ok(bincode::deserialize(&fs::read(path)?)?) };
+        Some(load())
+    }
+
+    fn load_verifying_key(&mut self, _size: usize, vk: &[u8]) -> Result<()> {
+        self.image_id = Digest::try_from(vk)?;
+        Ok(())
+    }
+
+    fn verifying_key(&self) -> Result<Vec<u8>> {
+        // receipts are verified against the image ID of the guest
+        Ok(self.image_id.as_bytes().to_vec())
+    }
+
+    fn proving_key_size(&self) -> Result<Option<usize>> {
+        // the risc0 circuit does not depend on the program, there is no proving key
+        Ok(None)
+    }
+
+    fn program_id(&self) -> Result<String> {
+        Ok(self.image_id.to_string())
+    }
+}
+
+fn main() {
+    harness::main(&mut Risc0Sha2::default());
+}
+
 #[cfg(test)]
 mod tests {
-    use sha_methods::{HASH_ID, HASH_RUST_CRYPTO_ID};
+    use super::*;
+
+    fn proven(input: &[u8]) -> Receipt {
+        let receipt = Risc0Sha2::default().prove(&input.to_vec()).unwrap();
+        Risc0Sha2::default().verify(&receipt).unwrap();
+        receipt
+    }
 
     #[test]
     fn hash_abc() {
-        let (digest, receipt) = super::bench_hash("abc", false);
-        receipt.verify(HASH_ID).unwrap();
+        let receipt = proven(b"abc");
+        let digest: Digest = receipt.journal.decode().unwrap();
         assert_eq!(
             hex::encode(digest.as_bytes()),
             "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
@@ -74,13 +155,39 @@ mod tests {
     }
 
     #[test]
-    fn hash_abc_rust_crypto() {
-        let (digest, receipt) = super::bench_hash("abc", true);
-        receipt.verify(HASH_RUST_CRYPTO_ID).unwrap();
-        assert_eq!(
-            hex::encode(digest.as_bytes()),
-            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
-            "We expect to match the reference SHA-256 hash of the standard test value 'abc'"
-        );
+    fn rejects_flipped_seal() {
+        let mut bytes = bincode::serialize(&proven(b"abc")).unwrap();
+        // the seal makes up nearly all of a serialized receipt
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        let receipt: Receipt = bincode::deserialize(&bytes).unwrap();
+        assert!(receipt.verify(HASH_ID).is_err());
+    }
+
+    #[test]
+    fn rejects_swapped_journal() {
+        let mut receipt = proven(b"abc");
+        receipt.journal.bytes[0] ^= 1;
+        assert!(receipt.verify(HASH_ID).is_err());
+    }
+
+    #[test]
+    fn verifies_with_a_loaded_image_id() {
+        let receipt = proven(b"abc");
+        let mut loaded = Risc0Sha2::default();
+        let mut image_id = Digest::from(HASH_ID).as_bytes().to_vec();
+        loaded.load_verifying_key(3, &image_id).unwrap();
+        loaded.verify(&receipt).unwrap();
+        image_id[0] ^= 1;
+        loaded.load_verifying_key(3, &image_id).unwrap();
+        assert!(loaded.verify(&receipt).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_image_id() {
+        let receipt = proven(b"abc");
+        let mut image_id = HASH_ID;
+        image_id[0] ^= 1;
+        assert!(receipt.verify(image_id).is_err());
     }
 }
@@ -1,51 +1,219 @@
-use std::time::Instant;
-
-use rand::Rng;
-use sp1_sdk::{utils, ProverClient, SP1Stdin};
+use anyhow::{anyhow, Result};
+use harness::{reference, Backend};
+use sp1_sdk::{ProverClient, SP1Proof, SP1ProvingKey, SP1Stdin, SP1VerifyingKey};
+use std::path::Path;
 
 /// The ELF we want to execute inside the zkVM.
 const ELF: &[u8] = include_bytes!("../../program/elf/riscv32im-succinct-zkvm-elf");
 
-fn main() {
-    utils::setup_logger();
+/// The sp1 git rev pinned in Cargo.toml.
+const SP1_REV: &str = "b79e2c2005da105b5abe53fce1b13747b948d4f5";
 
-    let mut rng = rand::thread_rng();
-    for n in [10, 100, 1000, 10_000, 100_000] {
-        let mut stdin = SP1Stdin::new();
-        // let input = vec![123u8; n]; //FIXME from rand str
-        let input: Vec<u8> = (0..n).map(|_| rng.gen()).collect();
-        stdin.write(&input);
+struct Sp1Sha2 {
+    client: Option<ProverClient>,
+    pk: Option<SP1ProvingKey>,
+    /// The key proofs are verified against: the one of keygen, or a loaded one.
+    vk: Option<SP1VerifyingKey>,
+}
+
+impl Sp1Sha2 {
+    fn client(&self) -> Result<&ProverClient> {
+        self.client
+            .as_ref()
+            .ok_or_else(|| anyhow!("setup has not been run"))
+    }
+
+    fn pk(&self) -> Result<&SP1ProvingKey> {
+        self.pk
+            .as_ref()
+            .ok_or_else(|| anyhow!("keygen has not been run"))
+    }
+
+    fn vk(&self) -> Result<&SP1VerifyingKey> {
+        self.vk
+            .as_ref()
+            .ok_or_else(|| anyhow!("keygen has not been run"))
+    }
+}
+
+impl Backend for Sp1Sha2 {
+    type Input = Vec<u8>;
+    type Proof = SP1Proof;
+
+    fn task(&self) -> &str {
+        "sha2"
+    }
 
-        // Generate the proof for the given program and input.
-        let client = ProverClient::new();
-        let (pk, vk) = client.setup(ELF);
-        let mut proof = client.prove(&pk, stdin).unwrap();
+    fn name(&self) -> &str {
+        "sp1"
+    }
 
-        println!("generated proof");
+    fn variant(&self) -> &str {
+        "core"
+    }
 
-        // Read and verify the output.
-        let _h = proof.public_values.read::<[u8; 32]>();
-        println!("h: {:?}", _h);
+    fn versions(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("sp1-sdk", SP1_REV)]
+    }
 
-        // Verify proof and public values
-        client.verify(&proof, &vk).expect("verification failed");
+    fn input(&self, size: usize, seed: u64) -> Vec<u8> {
+        harness::corpus::bytes(self.task(), seed, size)
+    }
 
-        // Save the proof.
-        proof
-            .save("proof-with-pis.json")
-            .expect("saving proof failed");
+    fn setup(&mut self, _size: usize) -> Result<()> {
+        self.client = Some(ProverClient::new());
+        Ok(())
+    }
+
+    fn keygen(&mut self, _size: usize) -> Result<()> {
+        let (pk, vk) = self.client()?.setup(ELF);
+        self.pk = Some(pk);
+        self.vk = Some(vk);
+        Ok(())
+    }
 
-        println!("successfully generated and verified proof for the program!");
+    fn execute(&self, input: &Vec<u8>) -> Option<Result<()>> {
+        let execute = || -> Result<()> {
+            let mut stdin = SP1Stdin::new();
+            stdin.write(input);
+            self.client()?.execute(ELF, stdin)?;
+            Ok(())
+        };
+        Some(execute())
+    }
 
-        //benchmark running
+    fn prove(&self, input: &Vec<u8>) -> Result<SP1Proof> {
+        let pk = self.pk()?;
         let mut stdin = SP1Stdin::new();
-        let input = vec![123u8; n]; //FIXME from rand str
-        stdin.write(&input);
-        let t = Instant::now();
-        let proof = client.prove(&pk, stdin).unwrap();
-        println!("=== sha2({}) Proving time: {:?}", n, t.elapsed());
-        let t = Instant::now();
-        client.verify(&proof, &vk).expect("verification failed");
-        println!("=== sha2({}) Verifying time: {:?}", n, t.elapsed());
+        stdin.write(input);
+        self.client()?.prove(pk, stdin)
+    }
+
+    fn verify(&self, proof: &SP1Proof) -> Result<()> {
+        let vk = self.vk()?;
+        self.client()?
+            .verify(proof, vk)
+            .map_err(|e| anyhow!("verification failed: {e:?}"))
+    }
+
+    fn check_output(&self, input: &Vec<u8>, proof: &SP1Proof) -> Result<()> {
+        let digest: [u8; 32] = bincode::deserialize(proof.public_values.as_slice())?;
+        reference::check("digest", reference::sha256(input), digest)
+    }
+
+    fn public_output(&self, proof: &SP1Proof) -> Result<Vec<u8>> {
+        Ok(proof.public_values.as_slice().to_vec())
+    }
+
+    fn serialize_proof(&self, proof: &SP1Proof) -> Result<Vec<u8>> {
+        // binary like the risc0 and halo2 proofs, so the proof sizes compare
+        Ok(bincode::serialize(proof)?)
+    }
+
+    fn save_proof(&self, proof: &SP1Proof, path: &Path) -> Result<()> {
+        // the proof JSON of the sp1 tooling, unlike the bincode bytes sized above
+        proof.save(path)
+    }
+
+    fn load_proof(&self, path: &Path) -> Option<Result<SP1Proof>> {
+        Some(SP1Proof::load(path))
+    }
+
+    fn load_verifying_key(&mut self, _size: usize, vk: &[u8]) -> Result<()> {
+        // the bincode key written by verifying_key
+        self.vk = Some(bincode::deserialize(vk)?);
+        Ok(())
+    }
+
+    fn verifying_key(&self) -> Result<Vec<u8>> {
+        let vk = self.vk()?;
+        Ok(bincode::serialize(vk)?)
+    }
+
+    fn proving_key_size(&self) -> Result<Option<usize>> {
+        let pk = self.pk()?;
+        Ok(Some(bincode::serialized_size(pk)? as usize))
+    }
+}
+
+fn main() {
+    // like sp1's utils::setup_logger, but on stderr: stdout carries the records when there
+    // is no --output
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let mut sha2 = Sp1Sha2 {
+        client: None,
+        pk: None,
+        vk: None,
+    };
+    harness::main(&mut sha2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp1_sdk::SP1PublicValues;
+
+    /// A backend keyed for the program and a proof of `input`.
+    fn proven(input: &[u8]) -> (Sp1Sha2, SP1Proof) {
+        let mut backend = Sp1Sha2 {
+            client: None,
+            pk: None,
+            vk: None,
+        };
+        backend.setup(0).unwrap();
+        backend.keygen(0).unwrap();
+        let proof = backend.prove(&input.to_vec()).unwrap();
+        backend.verify(&proof).unwrap();
+        (backend, proof)
+    }
+
+    #[test]
+    fn verifies_with_a_loaded_key() {
+        let (backend, proof) = proven(b"abc");
+        let mut loaded = Sp1Sha2 {
+            client: None,
+            pk: None,
+            vk: None,
+        };
+        loaded.setup(0).unwrap();
+        loaded
+            .load_verifying_key(0, &backend.verifying_key().unwrap())
+            .unwrap();
+        loaded.verify(&proof).unwrap();
+    }
+
+    #[test]
+    fn rejects_flipped_proof() {
+        let (backend, proof) = proven(b"abc");
+        let mut bytes = bincode::serialize(&proof).unwrap();
+        // the shard proofs make up nearly all of a serialized proof
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        let proof: SP1Proof = bincode::deserialize(&bytes).unwrap();
+        assert!(backend.verify(&proof).is_err());
+    }
+
+    #[test]
+    fn rejects_swapped_public_values() {
+        let (backend, mut proof) = proven(b"abc");
+        let mut values = proof.public_values.as_slice().to_vec();
+        values[0] ^= 1;
+        proof.public_values = SP1PublicValues::from(&values);
+        assert!(backend.verify(&proof).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_verifying_key() {
+        let (backend, proof) = proven(b"abc");
+        let vk = backend.vk().unwrap();
+        let mut bytes = bincode::serialize(vk).unwrap();
+        // a serialized vk starts with the commitment to the preprocessed traces
+        bytes[0] ^= 1;
+        let vk: SP1VerifyingKey = bincode::deserialize(&bytes).unwrap();
+        assert!(backend.client().unwrap().verify(&proof, &vk).is_err());
     }
 }